@@ -0,0 +1,240 @@
+use crate::camera::Camera;
+use crate::vector::Vec3;
+use std::f64::consts::PI;
+
+// Inclinación de la órbita del turntable: la cámara mira el centro desde arriba
+const TURNTABLE_ELEVATION: f64 = 30.0;
+const TURNTABLE_FOV: f64 = 50.0;
+
+// Estado de la cámara en un instante
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub position: Vec3,
+    pub look_at: Vec3,
+    pub fov: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    // Pasa por todos los fotogramas clave
+    CatmullRom,
+    // Los fotogramas clave son los puntos de control de una única curva de Bézier:
+    // solo pasa por el primero y el último, pero el movimiento es más suave
+    Bezier,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "catmull-rom" => Some(Interpolation::CatmullRom),
+            "bezier" => Some(Interpolation::Bezier),
+            _ => None,
+        }
+    }
+}
+
+pub enum CameraAnimation {
+    Keyframes {
+        keyframes: Vec<Keyframe>,
+        interpolation: Interpolation,
+    },
+    // Una vuelta completa alrededor de center en duration segundos
+    Turntable {
+        center: Vec3,
+        radius: f64,
+        duration: f64,
+    },
+}
+
+impl CameraAnimation {
+    // Archivo de texto con una línea por fotograma clave:
+    // tiempo  x y z  mira_x mira_y mira_z  fov
+    // Las líneas vacías y las que empiezan por # se ignoran
+    pub fn load(path: &str, interpolation: Interpolation) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("No se pudo leer la trayectoria de cámara {}: {}", path, err))?;
+
+        let mut keyframes = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<f64> = line
+                .split_whitespace()
+                .map(|value| value.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("{}:{}: número inválido", path, number + 1))?;
+            if values.len() != 8 {
+                return Err(format!("{}:{}: se esperaban 8 valores y hay {}", path, number + 1, values.len()));
+            }
+            keyframes.push(Keyframe {
+                time: values[0],
+                position: Vec3::new(values[1], values[2], values[3]),
+                look_at: Vec3::new(values[4], values[5], values[6]),
+                fov: values[7],
+            });
+        }
+
+        if keyframes.is_empty() {
+            return Err(format!("La trayectoria de cámara {} no tiene fotogramas clave", path));
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Ok(CameraAnimation::Keyframes { keyframes, interpolation })
+    }
+
+    // Cámara quieta durante duration segundos, para timelapses
+    pub fn still(position: Vec3, look_at: Vec3, fov: f64, duration: f64) -> Self {
+        let keyframe = Keyframe { time: 0.0, position, look_at, fov };
+        CameraAnimation::Keyframes {
+            keyframes: vec![keyframe, Keyframe { time: duration, ..keyframe }],
+            interpolation: Interpolation::CatmullRom,
+        }
+    }
+
+    // Órbita que encuadra una caja de min a max
+    pub fn turntable(min: Vec3, max: Vec3, duration: f64) -> Self {
+        let center = (min + max) / 2.0;
+        let half_diagonal = (max - min).length() / 2.0;
+        let radius = half_diagonal / (TURNTABLE_FOV.to_radians() / 2.0).sin();
+        CameraAnimation::Turntable { center, radius, duration }
+    }
+
+    // Número de fotogramas a fps. La vuelta del turntable no repite el
+    // primero al final, para que el vídeo se pueda reproducir en bucle
+    pub fn frame_count(&self, fps: f64) -> u32 {
+        let frames = (self.duration() * fps).round() as u32;
+        match self {
+            CameraAnimation::Keyframes { .. } => frames + 1,
+            CameraAnimation::Turntable { .. } => frames.max(1),
+        }
+    }
+
+    // Saltos entre fotogramas de un ciclo completo: en el turntable el último
+    // enlaza con el primero al repetirse, y con fotogramas clave el último cierra el ciclo
+    pub fn frame_steps(&self, fps: f64) -> u32 {
+        let frames = self.frame_count(fps);
+        match self {
+            CameraAnimation::Keyframes { .. } => frames.saturating_sub(1).max(1),
+            CameraAnimation::Turntable { .. } => frames,
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        match self {
+            CameraAnimation::Keyframes { keyframes, .. } => {
+                keyframes[keyframes.len() - 1].time - keyframes[0].time
+            }
+            CameraAnimation::Turntable { duration, .. } => *duration,
+        }
+    }
+
+    // Cámara a los time segundos del comienzo de la animación
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        match self {
+            CameraAnimation::Keyframes { keyframes, interpolation } => {
+                let time = keyframes[0].time + time;
+                match interpolation {
+                    Interpolation::CatmullRom => catmull_rom_at(keyframes, time),
+                    Interpolation::Bezier => bezier_at(keyframes, time),
+                }
+            }
+            CameraAnimation::Turntable { center, radius, duration } => {
+                let angle = 2.0 * PI * time / duration;
+                let elevation = TURNTABLE_ELEVATION.to_radians();
+                let offset = Vec3::new(
+                    angle.cos() * elevation.cos(),
+                    elevation.sin(),
+                    angle.sin() * elevation.cos(),
+                );
+                Keyframe {
+                    time,
+                    position: *center + offset * *radius,
+                    look_at: *center,
+                    fov: TURNTABLE_FOV,
+                }
+            }
+        }
+    }
+
+    pub fn camera_at(&self, time: f64, aspect_ratio: f64) -> Camera {
+        let keyframe = self.keyframe_at(time);
+        Camera::new(
+            keyframe.position,
+            keyframe.look_at,
+            Vec3::new(0.0, 1.0, 0.0),
+            keyframe.fov,
+            aspect_ratio,
+        )
+    }
+}
+
+// Catmull-Rom uniforme por tramos; en los extremos se repite el fotograma clave
+fn catmull_rom_at(keyframes: &[Keyframe], time: f64) -> Keyframe {
+    let last = keyframes.len() - 1;
+    if last == 0 {
+        return Keyframe { time, ..keyframes[0] };
+    }
+    let segment = keyframes
+        .iter()
+        .rposition(|keyframe| keyframe.time <= time)
+        .unwrap_or(0)
+        .min(last - 1);
+
+    let k1 = &keyframes[segment];
+    let k2 = &keyframes[segment + 1];
+    let k0 = &keyframes[segment.saturating_sub(1)];
+    let k3 = &keyframes[(segment + 2).min(last)];
+    let span = k2.time - k1.time;
+    let s = if span > 0.0 { ((time - k1.time) / span).clamp(0.0, 1.0) } else { 0.0 };
+
+    Keyframe {
+        time,
+        position: catmull_rom(k0.position, k1.position, k2.position, k3.position, s),
+        look_at: catmull_rom(k0.look_at, k1.look_at, k2.look_at, k3.look_at, s),
+        fov: catmull_rom_scalar(k0.fov, k1.fov, k2.fov, k3.fov, s),
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, s: f64) -> Vec3 {
+    Vec3::new(
+        catmull_rom_scalar(p0.x, p1.x, p2.x, p3.x, s),
+        catmull_rom_scalar(p0.y, p1.y, p2.y, p3.y, s),
+        catmull_rom_scalar(p0.z, p1.z, p2.z, p3.z, s),
+    )
+}
+
+fn catmull_rom_scalar(p0: f64, p1: f64, p2: f64, p3: f64, s: f64) -> f64 {
+    let s2 = s * s;
+    let s3 = s2 * s;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * s
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * s2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * s3)
+}
+
+// De Casteljau sobre todos los fotogramas clave con el tiempo normalizado
+fn bezier_at(keyframes: &[Keyframe], time: f64) -> Keyframe {
+    let first = keyframes[0].time;
+    let span = keyframes[keyframes.len() - 1].time - first;
+    let s = if span > 0.0 { ((time - first) / span).clamp(0.0, 1.0) } else { 0.0 };
+
+    let mut points: Vec<(Vec3, Vec3, f64)> = keyframes
+        .iter()
+        .map(|keyframe| (keyframe.position, keyframe.look_at, keyframe.fov))
+        .collect();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| {
+                let (a, b) = (pair[0], pair[1]);
+                (a.0 + (b.0 - a.0) * s, a.1 + (b.1 - a.1) * s, a.2 + (b.2 - a.2) * s)
+            })
+            .collect();
+    }
+
+    let (position, look_at, fov) = points[0];
+    Keyframe { time, position, look_at, fov }
+}
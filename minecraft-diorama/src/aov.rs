@@ -0,0 +1,151 @@
+use crate::camera::Camera;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use png::{BitDepth, ColorType};
+
+// Pases auxiliares del render para composición y datasets. Se calculan con
+// un único rayo por el centro de cada píxel, así que no tienen antialiasing
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aov {
+    // Distancia a lo largo del eje de la cámara
+    Depth,
+    // Normal geométrica en el mundo
+    Normal,
+    // Color de la textura sin luz, con el tinte del bioma como al sombrear (Material::sample)
+    Albedo,
+    // Índice de la cara más uno; 0 es el cielo
+    Face,
+    // Identificador del bloque por su nombre; 0 es el cielo
+    Material,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 5] = [Aov::Depth, Aov::Normal, Aov::Albedo, Aov::Face, Aov::Material];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Aov::ALL.into_iter().find(|aov| aov.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Face => "face",
+            Aov::Material => "material",
+        }
+    }
+
+    // Formato del pase en un PNG normal
+    pub fn png_format(self) -> (ColorType, BitDepth) {
+        match self {
+            Aov::Depth | Aov::Material => (ColorType::Grayscale, BitDepth::Sixteen),
+            Aov::Normal | Aov::Albedo => (ColorType::Rgb, BitDepth::Eight),
+            Aov::Face => (ColorType::Grayscale, BitDepth::Eight),
+        }
+    }
+
+    // Bytes de un píxel en el PNG: la profundidad se normaliza a 16 bits con
+    // max_depth en blanco, las normales van de [-1, 1] a [0, 255] y los
+    // identificadores se guardan tal cual
+    pub fn png_pixel(self, sample: &AovSample, max_depth: f32, bytes: &mut Vec<u8>) {
+        match self {
+            Aov::Depth => {
+                let depth = sample.depth.min(max_depth);
+                bytes.extend_from_slice(&((depth / max_depth * 65535.0).round() as u16).to_be_bytes());
+            }
+            Aov::Normal if sample.face == 0 => bytes.extend_from_slice(&[0; 3]),
+            Aov::Normal => bytes.extend(sample.normal.map(|n| ((n * 0.5 + 0.5) * 255.0).round() as u8)),
+            Aov::Albedo => bytes.extend(sample.albedo.map(|c| (c * 255.0).round() as u8)),
+            Aov::Face => bytes.push(sample.face as u8),
+            Aov::Material => bytes.extend_from_slice(&(sample.material as u16).to_be_bytes()),
+        }
+    }
+
+    // Capa del pase en un EXR y sus canales, en el orden alfabético que pide el
+    // formato. Los identificadores son enteros y el resto, f32
+    pub fn exr_layer(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Aov::Depth => ("depth", &["Z"]),
+            Aov::Normal => ("normal", &["B", "G", "R"]),
+            Aov::Albedo => ("albedo", &["B", "G", "R"]),
+            Aov::Face => ("face", &["id"]),
+            Aov::Material => ("material", &["id"]),
+        }
+    }
+
+    pub fn is_id(self) -> bool {
+        matches!(self, Aov::Face | Aov::Material)
+    }
+
+    // Valor del canal channel de exr_layer en un píxel; los identificadores,
+    // con exr_id
+    pub fn exr_value(self, sample: &AovSample, channel: usize) -> f32 {
+        match self {
+            Aov::Depth => sample.depth,
+            Aov::Normal => sample.normal[2 - channel],
+            Aov::Albedo => sample.albedo[2 - channel],
+            Aov::Face | Aov::Material => self.exr_id(sample) as f32,
+        }
+    }
+
+    pub fn exr_id(self, sample: &AovSample) -> u32 {
+        match self {
+            Aov::Face => sample.face,
+            Aov::Material => sample.material,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct AovSample {
+    pub depth: f32,
+    pub normal: [f32; 3],
+    pub albedo: [f32; 3],
+    pub face: u32,
+    pub material: u32,
+}
+
+impl AovSample {
+    // Lo que ve el rayo primario ray de camera
+    pub fn trace(ray: &Ray, camera: &Camera, scene: &Scene, materials: &[String]) -> Self {
+        let Some(hit) = scene.intersect(ray, 0.001, f64::INFINITY) else {
+            return AovSample {
+                depth: f32::INFINITY,
+                normal: [0.0; 3],
+                albedo: [0.0; 3],
+                face: 0,
+                material: 0,
+            };
+        };
+        // Sin huella, la textura se lee en su nivel de detalle más fino
+        let color = hit.material.sample(hit.face, hit.u, hit.v, 0.0, scene.time, &hit.biome);
+        let material = materials.binary_search(&hit.material.name).map_or(0, |index| index as u32 + 1);
+        AovSample {
+            depth: camera.depth(ray, hit.t) as f32,
+            normal: [hit.normal.x as f32, hit.normal.y as f32, hit.normal.z as f32],
+            albedo: [color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0],
+            face: hit.face as u32 + 1,
+            material,
+        }
+    }
+}
+
+// Muestras de una imagen entera, como guía del denoiser
+pub struct AovBuffers {
+    pub width: u32,
+    pub height: u32,
+    samples: Vec<AovSample>,
+}
+
+impl AovBuffers {
+    pub fn new(width: u32, height: u32, samples: Vec<AovSample>) -> Self {
+        AovBuffers { width, height, samples }
+    }
+
+    // Muestra del píxel index en orden de filas
+    pub fn sample(&self, index: usize) -> &AovSample {
+        &self.samples[index]
+    }
+}
@@ -0,0 +1,139 @@
+use crate::ray::Ray;
+use crate::rng::Rng;
+use crate::scene::Scene;
+use crate::sky::Sky;
+use crate::vector::Vec3;
+use std::f64::consts::PI;
+
+// Niebla exponencial por distancia, teñida con el color del cielo
+#[derive(Clone, Copy)]
+pub struct DistanceFog {
+    pub density: f64,
+}
+
+// Niebla densa bajo el agua con absorción de Beer–Lambert por canal
+#[derive(Clone, Copy)]
+pub struct WaterFog {
+    pub density: f64,
+    // Absorción relativa por canal: el agua absorbe primero el rojo
+    pub absorption: [f64; 3],
+    pub color: [f64; 3],
+}
+
+// Rayos de luz volumétricos (dispersión simple) para el render offline
+#[derive(Clone, Copy)]
+pub struct LightShafts {
+    pub density: f64,
+    pub steps: u32,
+    pub max_distance: f64,
+    // Anisotropía de Henyey–Greenstein: > 0 dispersa hacia delante
+    pub anisotropy: f64,
+}
+
+pub struct Atmosphere {
+    pub fog: Option<DistanceFog>,
+    pub water_fog: Option<WaterFog>,
+    pub light_shafts: Option<LightShafts>,
+}
+
+impl DistanceFog {
+    pub fn new(density: f64) -> Self {
+        DistanceFog { density }
+    }
+}
+
+impl WaterFog {
+    pub fn new(density: f64) -> Self {
+        WaterFog {
+            density,
+            absorption: [1.0, 0.45, 0.2],
+            color: [0.05, 0.25, 0.4],
+        }
+    }
+}
+
+impl LightShafts {
+    pub fn new(density: f64, steps: u32) -> Self {
+        LightShafts {
+            density,
+            steps: steps.max(1),
+            max_distance: 60.0,
+            anisotropy: 0.6,
+        }
+    }
+
+    // Integra la luz del sol dispersada a lo largo del rayo hasta t_max.
+    // Devuelve la transmitancia del tramo y la luz añadida.
+    pub fn march(&self, ray: &Ray, t_max: f64, scene: &Scene, rng: &mut Rng) -> ([f64; 3], [f64; 3]) {
+        let distance = t_max.min(self.max_distance);
+        let step = distance / self.steps as f64;
+        let (light_dir, light_color) = scene.sky.light();
+        let phase = henyey_greenstein(ray.direction.dot(&light_dir), self.anisotropy);
+
+        let mut inscatter = [0.0; 3];
+        let jitter = rng.next_f64();
+        for i in 0..self.steps {
+            let t = (i as f64 + jitter) * step;
+            let point = ray.at(t);
+            if scene.occluded(&Ray::new(point, light_dir), f64::INFINITY) {
+                continue;
+            }
+            let weight = self.density * phase * (-self.density * t).exp() * step;
+            for c in 0..3 {
+                inscatter[c] += light_color[c] * weight;
+            }
+        }
+
+        let transmittance = (-self.density * distance).exp();
+        ([transmittance; 3], inscatter)
+    }
+}
+
+impl Atmosphere {
+    pub fn new() -> Self {
+        Atmosphere {
+            fog: None,
+            water_fog: None,
+            light_shafts: None,
+        }
+    }
+
+    // Transmitancia y luz dispersada por la niebla en un tramo de longitud t.
+    // Bajo el agua solo se aplica la niebla del agua.
+    pub fn segment(&self, sky: &Sky, direction: &Vec3, t: f64, underwater: bool) -> ([f64; 3], [f64; 3]) {
+        if underwater {
+            if let Some(water) = &self.water_fog {
+                // La luz que llega bajo el agua sigue al ambiente del cielo
+                let ambient = sky.ambient();
+                let mut transmittance = [0.0; 3];
+                let mut inscatter = [0.0; 3];
+                for c in 0..3 {
+                    transmittance[c] = (-water.density * water.absorption[c] * t).exp();
+                    inscatter[c] = water.color[c] * ambient[c] * 2.5 * (1.0 - transmittance[c]);
+                }
+                return (transmittance, inscatter);
+            }
+            return ([1.0; 3], [0.0; 3]);
+        }
+
+        match &self.fog {
+            Some(fog) if t.is_finite() => {
+                let transmittance = (-fog.density * t).exp();
+                // El color de la niebla es el del cielo cerca del horizonte
+                let horizon = Vec3::new(direction.x, direction.y.max(0.0) * 0.3, direction.z);
+                let sky_color = sky.radiance(&horizon);
+                let amount = 1.0 - transmittance;
+                (
+                    [transmittance; 3],
+                    [sky_color[0] * amount, sky_color[1] * amount, sky_color[2] * amount],
+                )
+            }
+            _ => ([1.0; 3], [0.0; 3]),
+        }
+    }
+}
+
+fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
@@ -0,0 +1,58 @@
+use image::RgbImage;
+
+// Parámetros climáticos de un bioma, como en Minecraft
+#[derive(Clone, Copy)]
+pub struct Biome {
+    pub name: &'static str,
+    pub temperature: f64,
+    pub downfall: f64,
+}
+
+// Biomas vanilla con sus valores de temperatura y humedad
+pub const BIOMES: [Biome; 8] = [
+    Biome { name: "plains", temperature: 0.8, downfall: 0.4 },
+    Biome { name: "forest", temperature: 0.7, downfall: 0.8 },
+    Biome { name: "birch_forest", temperature: 0.6, downfall: 0.6 },
+    Biome { name: "taiga", temperature: 0.25, downfall: 0.8 },
+    Biome { name: "snowy_plains", temperature: 0.0, downfall: 0.5 },
+    Biome { name: "jungle", temperature: 0.95, downfall: 0.9 },
+    Biome { name: "savanna", temperature: 2.0, downfall: 0.0 },
+    Biome { name: "desert", temperature: 2.0, downfall: 0.0 },
+];
+
+impl Biome {
+    pub fn plains() -> Self {
+        BIOMES[0]
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        BIOMES.iter().find(|biome| biome.name == name).copied()
+    }
+
+    // El bioma siguiente de la lista, para recorrerlos desde el visor
+    pub fn next(&self) -> Self {
+        let index = BIOMES.iter().position(|biome| biome.name == self.name).unwrap_or(0);
+        BIOMES[(index + 1) % BIOMES.len()]
+    }
+}
+
+// Mapa de color de 256x256 (grass.png / foliage.png) indexado por clima
+pub struct Colormap {
+    image: RgbImage,
+}
+
+impl Colormap {
+    pub fn new(image: RgbImage) -> Self {
+        Colormap { image }
+    }
+
+    // Misma indexación que Minecraft: la humedad se escala por la temperatura
+    pub fn lookup(&self, biome: &Biome) -> [u8; 3] {
+        let temperature = biome.temperature.clamp(0.0, 1.0);
+        let downfall = biome.downfall.clamp(0.0, 1.0) * temperature;
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = (((1.0 - temperature) * (width - 1) as f64) as u32).min(width - 1);
+        let y = (((1.0 - downfall) * (height - 1) as f64) as u32).min(height - 1);
+        self.image.get_pixel(x, y).0
+    }
+}
@@ -0,0 +1,359 @@
+use crate::material::Texture;
+use crate::model::{Axis, BlockModel, Element, ElementFace, ElementRotation, PlacedModel};
+use crate::resource_pack::ResourcePack;
+use crate::vector::Vec3;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+// Límite de la cadena de "parent" y de referencias #variable, por si hay ciclos
+const MAX_INDIRECTIONS: usize = 16;
+
+// Propiedades de un bloque (facing=east, half=bottom...), como en los blockstates
+#[derive(Clone, Default)]
+pub struct BlockState {
+    properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    // Formato de las claves de "variants": "facing=east,half=bottom"
+    pub fn parse(text: &str) -> Self {
+        let mut state = BlockState::default();
+        for pair in text.split(',').filter(|pair| !pair.is_empty()) {
+            if let Some((key, value)) = pair.split_once('=') {
+                state.set(key.trim(), value.trim());
+            }
+        }
+        state
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.properties.insert(key.to_string(), value.to_string());
+    }
+
+    // Quita la propiedad y devuelve su valor; sirve para las que no son del bloque
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.properties.remove(key)
+    }
+
+    // Sobrescribe las propiedades con las de other
+    pub fn apply(&mut self, other: &BlockState) {
+        for (key, value) in &other.properties {
+            self.set(key, value);
+        }
+    }
+
+    // Clave de "variants": las propiedades que el bloque no tiene valen cualquier valor
+    fn matches_variant(&self, key: &str) -> bool {
+        BlockState::parse(key)
+            .properties
+            .iter()
+            .all(|(property, value)| self.get(property).is_none_or(|own| own == value))
+    }
+
+    // Condición "when" de multipart: "OR"/"AND" o propiedades con alternativas "a|b"
+    fn matches_when(&self, when: &Value) -> bool {
+        let Some(conditions) = when.as_object() else {
+            return true;
+        };
+        if let Some(list) = conditions.get("OR").and_then(Value::as_array) {
+            return list.iter().any(|condition| self.matches_when(condition));
+        }
+        if let Some(list) = conditions.get("AND").and_then(Value::as_array) {
+            return list.iter().all(|condition| self.matches_when(condition));
+        }
+        conditions.iter().all(|(property, expected)| {
+            let expected = match expected {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            self.get(property)
+                .is_some_and(|own| expected.split('|').any(|option| option == own))
+        })
+    }
+}
+
+// Bloque construido desde JSON. Las caras de los elementos apuntan a huecos de
+// textura del material, así que estos vectores sustituyen a los del material.
+pub struct JsonBlock {
+    pub textures: Vec<Option<Texture>>,
+    pub tinted: Vec<bool>,
+    pub overlays: Vec<Option<Texture>>,
+    pub models: Vec<PlacedModel>,
+}
+
+// Modelo con la herencia de "parent" ya aplicada
+struct ResolvedModel {
+    textures: HashMap<String, String>,
+    elements: Vec<Value>,
+}
+
+// Huecos de textura de un bloque, uno por textura distinta
+#[derive(Default)]
+struct TextureSlots {
+    names: Vec<Option<String>>,
+    tinted: Vec<bool>,
+    overlays: Vec<Option<String>>,
+}
+
+impl TextureSlots {
+    fn slot(&mut self, name: Option<String>, tinted: bool) -> usize {
+        let index = match self.names.iter().position(|existing| *existing == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(name);
+                self.tinted.push(false);
+                self.overlays.push(None);
+                self.names.len() - 1
+            }
+        };
+        self.tinted[index] |= tinted;
+        index
+    }
+}
+
+// Lee blockstates/*.json y models/block/*.json de un paquete de recursos
+pub struct BlockLoader<'a> {
+    pack: &'a ResourcePack,
+    models: RefCell<HashMap<String, Option<Arc<ResolvedModel>>>>,
+    // Bloques ya construidos por nombre y estado
+    blocks: RefCell<HashMap<String, Option<Arc<JsonBlock>>>>,
+}
+
+impl<'a> BlockLoader<'a> {
+    pub fn new(pack: &'a ResourcePack) -> Self {
+        BlockLoader {
+            pack,
+            models: RefCell::new(HashMap::new()),
+            blocks: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // None si el paquete no describe el bloque o su modelo no tiene elementos (agua)
+    pub fn load(&self, block: &str, state: &BlockState) -> Option<Arc<JsonBlock>> {
+        let key = format!("{}{:?}", block, state.properties);
+        if let Some(cached) = self.blocks.borrow().get(&key) {
+            return cached.clone();
+        }
+        let loaded = self.build_block(block, state).map(Arc::new);
+        self.blocks.borrow_mut().insert(key, loaded.clone());
+        loaded
+    }
+
+    fn build_block(&self, block: &str, state: &BlockState) -> Option<JsonBlock> {
+        let path = format!("assets/minecraft/blockstates/{}.json", block);
+        let json = self.read_json(&path)?;
+
+        let mut placements = Vec::new();
+        if let Some(variants) = json.get("variants").and_then(Value::as_object) {
+            let (_, variant) = variants
+                .iter()
+                .find(|(key, _)| *key == "normal" || state.matches_variant(key))
+                .or_else(|| variants.iter().next())?;
+            placements.extend(parse_apply(variant));
+        } else if let Some(parts) = json.get("multipart").and_then(Value::as_array) {
+            for part in parts {
+                if part.get("when").is_none_or(|when| state.matches_when(when)) {
+                    placements.extend(part.get("apply").and_then(parse_apply));
+                }
+            }
+        }
+
+        let mut slots = TextureSlots::default();
+        let mut models = Vec::new();
+        for (model_name, x_rotation, y_rotation) in placements {
+            let Some(resolved) = self.resolve_model(&model_name, 0) else {
+                println!("✗ Advertencia: no se encontró el modelo {} del bloque {}", model_name, block);
+                continue;
+            };
+            let model = build_model(&resolved, &mut slots);
+            if !model.elements.is_empty() {
+                models.push(PlacedModel::new(Arc::new(model), x_rotation, y_rotation));
+            }
+        }
+        if models.is_empty() {
+            return None;
+        }
+
+        let load = |name: &Option<String>| name.as_deref().and_then(|name| self.pack.texture_resource(name));
+        Some(JsonBlock {
+            textures: slots.names.iter().map(load).collect(),
+            tinted: slots.tinted,
+            overlays: slots.overlays.iter().map(load).collect(),
+            models,
+        })
+    }
+
+    fn resolve_model(&self, name: &str, depth: usize) -> Option<Arc<ResolvedModel>> {
+        let name = model_resource(name);
+        if let Some(cached) = self.models.borrow().get(&name) {
+            return cached.clone();
+        }
+
+        let json = self.read_json(&format!("assets/minecraft/models/{}.json", name));
+        let resolved = json.map(|json| {
+            let mut textures = HashMap::new();
+            let mut elements = Vec::new();
+            let parent = json.get("parent").and_then(Value::as_str);
+            if let Some(parent) = parent.filter(|parent| !parent.starts_with("builtin/")) {
+                if let Some(parent) = (depth < MAX_INDIRECTIONS).then(|| self.resolve_model(parent, depth + 1)).flatten() {
+                    textures = parent.textures.clone();
+                    elements = parent.elements.clone();
+                }
+            }
+            if let Some(own) = json.get("textures").and_then(Value::as_object) {
+                for (key, value) in own {
+                    if let Some(value) = value.as_str() {
+                        textures.insert(key.clone(), value.to_string());
+                    }
+                }
+            }
+            if let Some(own) = json.get("elements").and_then(Value::as_array) {
+                elements = own.clone();
+            }
+            Arc::new(ResolvedModel { textures, elements })
+        });
+
+        self.models.borrow_mut().insert(name, resolved.clone());
+        resolved
+    }
+
+    fn read_json(&self, path: &str) -> Option<Value> {
+        let bytes = self.pack.read(path)?;
+        match serde_json::from_slice(&bytes) {
+            Ok(json) => Some(json),
+            Err(err) => {
+                println!("✗ Advertencia: JSON inválido en {}: {}", path, err);
+                None
+            }
+        }
+    }
+}
+
+// "minecraft:block/stone" -> "block/stone"; los nombres antiguos sin carpeta van a block/
+fn model_resource(name: &str) -> String {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    if name.contains('/') {
+        name.to_string()
+    } else {
+        format!("block/{}", name)
+    }
+}
+
+// Valor de una variante o de "apply": un objeto o una lista ponderada (se toma el primero)
+fn parse_apply(value: &Value) -> Option<(String, i32, i32)> {
+    let value = match value {
+        Value::Array(options) => options.first()?,
+        other => other,
+    };
+    let model = value.get("model")?.as_str()?.to_string();
+    let rotation = |key: &str| value.get(key).and_then(Value::as_i64).unwrap_or(0) as i32;
+    Some((model, rotation("x"), rotation("y")))
+}
+
+fn build_model(resolved: &ResolvedModel, slots: &mut TextureSlots) -> BlockModel {
+    let mut elements: Vec<Element> = Vec::new();
+    for json in &resolved.elements {
+        let (Some(from), Some(to)) = (parse_vec3(json.get("from")), parse_vec3(json.get("to"))) else {
+            continue;
+        };
+        let rotation = json.get("rotation").and_then(parse_rotation);
+
+        // Un elemento con la misma caja que otro anterior es una capa superpuesta
+        // (el césped lateral de grass_block): sus caras pasan a ser overlays
+        let base = elements.iter().position(|element| {
+            rotation.is_none() && element.rotation.is_none() && same_point(&element.from, &from) && same_point(&element.to, &to)
+        });
+
+        let mut faces = [None; 6];
+        let face_list = json.get("faces").and_then(Value::as_object);
+        for (name, face_json) in face_list.into_iter().flatten() {
+            let Some(face) = face_index(name) else {
+                continue;
+            };
+            let texture = face_json
+                .get("texture")
+                .and_then(Value::as_str)
+                .and_then(|reference| resolve_texture(&resolved.textures, reference));
+            let tinted = face_json.get("tintindex").is_some();
+
+            if let Some(base) = base {
+                if let Some(base_face) = elements[base].faces[face] {
+                    slots.overlays[base_face.texture] = texture;
+                }
+                continue;
+            }
+
+            faces[face] = Some(ElementFace {
+                uv: face_json.get("uv").and_then(parse_uv),
+                texture: slots.slot(texture, tinted),
+                rotation: face_json.get("rotation").and_then(Value::as_i64).unwrap_or(0) as i32,
+            });
+        }
+
+        if base.is_none() {
+            elements.push(Element { from, to, rotation, faces });
+        }
+    }
+    BlockModel::new(elements)
+}
+
+// Sigue las referencias "#variable" hasta llegar a una textura
+fn resolve_texture(textures: &HashMap<String, String>, reference: &str) -> Option<String> {
+    let mut current = reference.to_string();
+    for _ in 0..MAX_INDIRECTIONS {
+        match current.strip_prefix('#') {
+            Some(variable) => current = textures.get(variable)?.clone(),
+            None => return Some(current),
+        }
+    }
+    None
+}
+
+fn parse_rotation(json: &Value) -> Option<ElementRotation> {
+    let axis = match json.get("axis")?.as_str()? {
+        "x" => Axis::X,
+        "y" => Axis::Y,
+        "z" => Axis::Z,
+        _ => return None,
+    };
+    Some(ElementRotation {
+        origin: parse_vec3(json.get("origin")).unwrap_or(Vec3::new(8.0, 8.0, 8.0)),
+        axis,
+        angle: json.get("angle").and_then(Value::as_f64).unwrap_or(0.0),
+        rescale: json.get("rescale").and_then(Value::as_bool).unwrap_or(false),
+    })
+}
+
+fn parse_vec3(json: Option<&Value>) -> Option<Vec3> {
+    let list = json?.as_array()?;
+    let value = |i: usize| list.get(i).and_then(Value::as_f64);
+    Some(Vec3::new(value(0)?, value(1)?, value(2)?))
+}
+
+fn parse_uv(json: &Value) -> Option<[f64; 4]> {
+    let list = json.as_array()?;
+    let value = |i: usize| list.get(i).and_then(Value::as_f64);
+    Some([value(0)?, value(1)?, value(2)?, value(3)?])
+}
+
+fn face_index(name: &str) -> Option<usize> {
+    match name {
+        "up" => Some(0),
+        "down" => Some(1),
+        "north" => Some(2),
+        "south" => Some(3),
+        "east" => Some(4),
+        "west" => Some(5),
+        _ => None,
+    }
+}
+
+fn same_point(a: &Vec3, b: &Vec3) -> bool {
+    (a.x - b.x).abs() < 1e-6 && (a.y - b.y).abs() < 1e-6 && (a.z - b.z).abs() < 1e-6
+}
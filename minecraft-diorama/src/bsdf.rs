@@ -0,0 +1,167 @@
+use crate::rng::Rng;
+use crate::vector::Vec3;
+use std::f64::consts::PI;
+
+// Por debajo de este alfa el lóbulo GGX es un espejo y pierde precisión numérica
+const MIN_ALPHA: f64 = 0.002;
+
+// Punto de una superficie opaca con el modelo metálico/rugosidad: difuso de Lambert
+// más especular de microfacetas GGX (sombreado de Smith, Fresnel de Schlick).
+// Los vectores view y light salen de la superficie.
+pub struct Surface {
+    // Normal de sombreado, ya con el mapa de normales aplicado
+    pub normal: Vec3,
+    pub albedo: [f64; 3],
+    pub roughness: f64,
+    pub metallic: f64,
+    pub f0: f64,
+}
+
+impl Surface {
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    // Los metales no tienen difuso y reflejan con el color del albedo
+    pub fn diffuse_color(&self) -> [f64; 3] {
+        self.albedo.map(|c| c * (1.0 - self.metallic))
+    }
+
+    fn specular_color(&self) -> [f64; 3] {
+        self.albedo.map(|c| self.f0 + (c - self.f0) * self.metallic)
+    }
+
+    // Reflectancia de Fresnel vista desde view, por canal
+    pub fn fresnel(&self, view: &Vec3) -> [f64; 3] {
+        schlick(self.specular_color(), self.normal.dot(view).max(0.0))
+    }
+
+    // BRDF especular por el coseno de la luz
+    pub fn specular(&self, view: &Vec3, light: &Vec3) -> [f64; 3] {
+        let n_dot_v = self.normal.dot(view);
+        let n_dot_l = self.normal.dot(light);
+        if n_dot_v <= 0.0 || n_dot_l <= 0.0 {
+            return [0.0; 3];
+        }
+        let half = (*view + *light).normalize();
+        let alpha = self.alpha();
+        let d = ggx_distribution(self.normal.dot(&half), alpha);
+        let g = smith_g1(n_dot_v, alpha) * smith_g1(n_dot_l, alpha);
+        let fresnel = schlick(self.specular_color(), view.dot(&half).max(0.0));
+        let factor = d * g / (4.0 * n_dot_v);
+        fresnel.map(|f| f * factor)
+    }
+
+    // BRDF completa por el coseno de la luz
+    pub fn eval(&self, view: &Vec3, light: &Vec3) -> [f64; 3] {
+        let n_dot_l = self.normal.dot(light);
+        if n_dot_l <= 0.0 {
+            return [0.0; 3];
+        }
+        let diffuse = self.diffuse_color();
+        let specular = self.specular(view, light);
+        [
+            diffuse[0] * n_dot_l / PI + specular[0],
+            diffuse[1] * n_dot_l / PI + specular[1],
+            diffuse[2] * n_dot_l / PI + specular[2],
+        ]
+    }
+
+    // Se elige el lóbulo especular según su peso frente al difuso
+    fn specular_probability(&self, view: &Vec3) -> f64 {
+        let specular = average(self.fresnel(view));
+        let diffuse = average(self.diffuse_color());
+        if specular + diffuse <= 0.0 {
+            0.5
+        } else {
+            specular / (specular + diffuse)
+        }
+    }
+
+    // Densidad de probabilidad de sample para la dirección light
+    pub fn pdf(&self, view: &Vec3, light: &Vec3) -> f64 {
+        let n_dot_l = self.normal.dot(light);
+        let n_dot_v = self.normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return 0.0;
+        }
+        let alpha = self.alpha();
+        let half = (*view + *light).normalize();
+        // pdf de las normales visibles convertida a dirección reflejada
+        let specular_pdf = smith_g1(n_dot_v, alpha) * ggx_distribution(self.normal.dot(&half), alpha) / (4.0 * n_dot_v);
+        let diffuse_pdf = n_dot_l / PI;
+        let p = self.specular_probability(view);
+        p * specular_pdf + (1.0 - p) * diffuse_pdf
+    }
+
+    // Dirección de salida muestreada; None si queda por debajo de la superficie
+    pub fn sample(&self, view: &Vec3, rng: &mut Rng) -> Option<Vec3> {
+        if rng.next_f64() < self.specular_probability(view) {
+            return self.sample_reflection(view, rng);
+        }
+        let direction = cosine_sample_hemisphere(&self.normal, rng);
+        (self.normal.dot(&direction) > 0.0).then_some(direction)
+    }
+
+    // Solo el lóbulo especular: view reflejado en una microfaceta visible
+    pub fn sample_reflection(&self, view: &Vec3, rng: &mut Rng) -> Option<Vec3> {
+        let half = self.sample_visible_normal(view, rng);
+        let direction = half * (2.0 * view.dot(&half)) - *view;
+        (self.normal.dot(&direction) > 0.0).then_some(direction)
+    }
+
+    // Muestreo de normales visibles de GGX (Heitz 2018)
+    fn sample_visible_normal(&self, view: &Vec3, rng: &mut Rng) -> Vec3 {
+        let alpha = self.alpha();
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let local = Vec3::new(view.dot(&tangent), view.dot(&bitangent), view.dot(&self.normal));
+
+        // Hemisferio estirado en el que el lóbulo es isótropo
+        let stretched = Vec3::new(alpha * local.x, alpha * local.y, local.z).normalize();
+        let length_sq = stretched.x * stretched.x + stretched.y * stretched.y;
+        let t1 = if length_sq > 0.0 {
+            Vec3::new(-stretched.y, stretched.x, 0.0) / length_sq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = stretched.cross(&t1);
+
+        let r = rng.next_f64().sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + stretched.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let n = t1 * p1 + t2 * p2 + stretched * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        let half = Vec3::new(alpha * n.x, alpha * n.y, n.z.max(1e-6)).normalize();
+        (tangent * half.x + bitangent * half.y + self.normal * half.z).normalize()
+    }
+}
+
+fn cosine_sample_hemisphere(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let phi = 2.0 * PI * rng.next_f64();
+    let r2 = rng.next_f64();
+    let r = r2.sqrt();
+    let (tangent, bitangent) = normal.orthonormal_basis();
+    tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + *normal * (1.0 - r2).sqrt()
+}
+
+fn ggx_distribution(n_dot_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * denom * denom)
+}
+
+fn smith_g1(n_dot_x: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    2.0 * n_dot_x / (n_dot_x + (a2 + (1.0 - a2) * n_dot_x * n_dot_x).sqrt())
+}
+
+fn schlick(f0: [f64; 3], cos_theta: f64) -> [f64; 3] {
+    let weight = (1.0 - cos_theta).powi(5);
+    f0.map(|f| f + (1.0 - f) * weight)
+}
+
+fn average(color: [f64; 3]) -> f64 {
+    (color[0] + color[1] + color[2]) / 3.0
+}
@@ -1,11 +1,91 @@
 use crate::vector::Vec3;
 use crate::ray::Ray;
+use crate::rng::Rng;
+use std::f64::consts::PI;
+
+// Proyección de la cámara. Isométrica y dimétrica son ortográficas con ángulos fijos
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+    Isometric,
+    Dimetric,
+}
+
+impl Projection {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "perspective" => Some(Projection::Perspective),
+            "orthographic" => Some(Projection::Orthographic),
+            "isometric" => Some(Projection::Isometric),
+            "dimetric" => Some(Projection::Dimetric),
+            _ => None,
+        }
+    }
+    
+    pub fn next(self) -> Self {
+        match self {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Isometric,
+            Projection::Isometric => Projection::Dimetric,
+            Projection::Dimetric => Projection::Perspective,
+        }
+    }
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Projection::Perspective => "perspective",
+            Projection::Orthographic => "orthographic",
+            Projection::Isometric => "isometric",
+            Projection::Dimetric => "dimetric",
+        }
+    }
+    
+    pub fn is_orthographic(self) -> bool {
+        self != Projection::Perspective
+    }
+    
+    // Inclinación hacia abajo en grados de los ángulos predefinidos: en la isométrica
+    // los tres ejes se ven iguales; en la dimétrica 2:1 del pixel art el suelo sube
+    // un píxel por cada dos en horizontal. Mirando a 45°, esa pendiente en pantalla
+    // es el seno de la inclinación, así que la dimétrica baja 30°
+    pub fn preset_pitch(self) -> Option<f64> {
+        match self {
+            Projection::Isometric => Some(-(0.5_f64.sqrt()).atan().to_degrees()),
+            Projection::Dimetric => Some(-(0.5_f64).asin().to_degrees()),
+            _ => None,
+        }
+    }
+}
+
+// Cómo se convierte (u, v) en un rayo
+#[derive(Clone, Copy, PartialEq)]
+enum Mapping {
+    Perspective,
+    // Rayos paralelos que salen de todo el plano de imagen
+    Orthographic,
+    // Panorama de 360°: u es el azimut desde el norte (-z) y v la altura,
+    // como en los mapas de entorno
+    Equirectangular,
+}
 
 pub struct Camera {
     origin: Vec3,
     lower_left_corner: Vec3,
     horizontal: Vec3,
     vertical: Vec3,
+    // Base de la cámara: derecha, arriba y hacia atrás
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    // Lente fina: radio de la apertura y distancia del plano enfocado.
+    // Con radio 0 la cámara es estenopeica
+    lens_radius: f64,
+    focus_distance: f64,
+    mapping: Mapping,
+    // Las imágenes de un panorama tienen que encajar sin solaparse: el campo de
+    // visión se reparte entre los píxeles enteros, de borde a borde
+    panorama: bool,
 }
 
 impl Camera {
@@ -35,13 +115,159 @@ impl Camera {
             lower_left_corner,
             horizontal,
             vertical,
+            u,
+            v,
+            w,
+            lens_radius: 0.0,
+            focus_distance: 1.0,
+            mapping: Mapping::Perspective,
+            panorama: false,
+        }
+    }
+    
+    // Proyección ortográfica: el plano de imagen pasa por look_from y mide
+    // view_height unidades de alto
+    pub fn orthographic(
+        look_from: Vec3,
+        look_at: Vec3,
+        vup: Vec3,
+        view_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let w = (look_from - look_at).normalize();
+        let u = vup.cross(&w).normalize();
+        let v = w.cross(&u);
+        
+        let horizontal = u * (view_height * aspect_ratio);
+        let vertical = v * view_height;
+        
+        Camera {
+            origin: look_from,
+            lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            u,
+            v,
+            w,
+            lens_radius: 0.0,
+            focus_distance: 1.0,
+            mapping: Mapping::Orthographic,
+            panorama: false,
+        }
+    }
+    
+    // Panorama equirectangular completo alrededor de origin (imagen 2:1)
+    pub fn equirectangular(origin: Vec3) -> Self {
+        Camera {
+            origin,
+            lower_left_corner: origin,
+            horizontal: Vec3::zero(),
+            vertical: Vec3::zero(),
+            u: Vec3::new(1.0, 0.0, 0.0),
+            v: Vec3::new(0.0, 1.0, 0.0),
+            w: Vec3::new(0.0, 0.0, 1.0),
+            lens_radius: 0.0,
+            focus_distance: 1.0,
+            mapping: Mapping::Equirectangular,
+            panorama: true,
+        }
+    }
+    
+    // Las seis caras de un cubemap alrededor de origin, de 90° y cuadradas, con el
+    // sufijo de cada una. Las laterales tienen +y arriba; la de arriba, -z y la de abajo, +z
+    pub fn cubemap(origin: Vec3) -> Vec<(&'static str, Camera)> {
+        let faces = [
+            ("px", Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            ("nx", Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            ("py", Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+            ("ny", Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            ("pz", Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+            ("nz", Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        faces
+            .into_iter()
+            .map(|(name, direction, up)| {
+                let camera = Camera::new(origin, origin + direction, up, 90.0, 1.0);
+                (name, Camera { panorama: true, ..camera })
+            })
+            .collect()
+    }
+    
+    // Profundidad de campo: el plano de imagen pasa a la distancia de enfoque
+    // y los rayos salen de un disco de diámetro aperture
+    pub fn with_lens(mut self, aperture: f64, focus_distance: f64) -> Self {
+        let scale = focus_distance / self.focus_distance;
+        self.horizontal = self.horizontal * scale;
+        self.vertical = self.vertical * scale;
+        self.lower_left_corner = self.origin - self.horizontal / 2.0 - self.vertical / 2.0 - self.w * focus_distance;
+        self.lens_radius = aperture / 2.0;
+        self.focus_distance = focus_distance;
+        self
+    }
+    
+    // Cono de un píxel (ancho inicial, crecimiento). En perspectiva es el ángulo
+    // (aprox.) que abarca, con el plano de imagen a distancia focus_distance;
+    // en ortográfica el cono no crece y mide lo que el píxel en el plano
+    pub fn pixel_cone(&self, image_height: u32) -> (f64, f64) {
+        let pixel = self.vertical.length() / image_height as f64;
+        match self.mapping {
+            Mapping::Perspective => (0.0, pixel / self.focus_distance),
+            Mapping::Orthographic => (pixel, 0.0),
+            Mapping::Equirectangular => (0.0, PI / image_height as f64),
+        }
+    }
+    
+    // (u, v) de un punto del píxel (x, y) de una imagen de width x height, con
+    // (dx, dy) en [0, 1) dentro del píxel. En los panoramas ninguna dirección se
+    // repite: la primera y la última columna del equirectangular no coinciden y
+    // las caras del cubemap cubren 90° justos, sin compartir bordes
+    pub fn pixel_uv(&self, (x, y): (u32, u32), (dx, dy): (f64, f64), width: u32, height: u32) -> (f64, f64) {
+        let (x, y) = (x as f64 + dx, (height - 1 - y) as f64 + dy);
+        if self.panorama {
+            (x / width as f64, y / height as f64)
+        } else {
+            (x / (width - 1) as f64, y / (height - 1) as f64)
         }
     }
     
     pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+        match self.mapping {
+            Mapping::Perspective => Ray::new(
+                self.origin,
+                self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin,
+            ),
+            Mapping::Orthographic => {
+                Ray::new(self.lower_left_corner + self.horizontal * u + self.vertical * v, -self.w)
+            }
+            Mapping::Equirectangular => {
+                let phi = u * 2.0 * PI;
+                let theta = (1.0 - v) * PI;
+                Ray::new(self.origin, Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos()))
+            }
+        }
+    }
+    
+    // Profundidad lineal de un impacto a distancia t sobre un rayo de la cámara:
+    // a lo largo del eje de visión, o la distancia sin más en los panoramas
+    pub fn depth(&self, ray: &Ray, t: f64) -> f64 {
+        match self.mapping {
+            Mapping::Perspective => t * ray.direction.dot(&-self.w),
+            Mapping::Orthographic | Mapping::Equirectangular => t,
+        }
+    }
+    
+    // Como get_ray, pero desde un punto aleatorio de la lente
+    pub fn sample_ray(&self, u: f64, v: f64, rng: &mut Rng) -> Ray {
+        if self.lens_radius <= 0.0 {
+            return self.get_ray(u, v);
+        }
+        let r = self.lens_radius * rng.next_f64().sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        let offset = self.u * (r * phi.cos()) + self.v * (r * phi.sin());
+        let origin = self.origin + offset;
         Ray::new(
-            self.origin,
-            self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin,
+            origin,
+            self.lower_left_corner + self.horizontal * u + self.vertical * v - origin,
         )
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,285 @@
+use crate::raytracer::TILE_SIZE;
+use crate::rng::Rng;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Cabecera de los archivos de checkpoint; el número es la versión del formato
+const MAGIC: &[u8; 8] = b"DIOCKPT2";
+// Cabecera más los campos de RenderKey
+const HEADER_SIZE: u64 = 8 + 9 * 4;
+// Suma (3 f64), muestras (u32) y estado del generador (u64)
+const PIXEL_SIZE: u64 = 36;
+
+// Lo que se lleva acumulado de un píxel: con la suma, el número de muestras y
+// el estado del generador se pueden añadir muestras más tarde y obtener lo
+// mismo que si se hubieran tomado todas seguidas
+#[derive(Clone)]
+pub struct PixelState {
+    pub sum: [f64; 3],
+    pub samples: u32,
+    pub rng: Rng,
+}
+
+impl PixelState {
+    pub fn new(x: u32, y: u32, seed: u32) -> Self {
+        PixelState { sum: [0.0; 3], samples: 0, rng: Rng::for_pixel(x, y, seed) }
+    }
+}
+
+// Lo que identifica un render: un checkpoint solo vale para el mismo. La
+// región va en píxeles de la imagen completa (x, y, ancho, alto)
+#[derive(Clone, Copy, PartialEq)]
+pub struct RenderKey {
+    pub width: u32,
+    pub height: u32,
+    pub region: [u32; 4],
+    pub seed: u32,
+    // 0 para Whitted y 1 para path tracing
+    pub integrator: u32,
+}
+
+impl RenderKey {
+    fn fields(&self) -> [u32; 9] {
+        let [x, y, width, height] = self.region;
+        [self.width, self.height, x, y, width, height, self.seed, self.integrator, TILE_SIZE]
+    }
+}
+
+// Bloque de una tesela ya codificado y su posición en el archivo
+type TileBlock = (u64, Vec<u8>);
+
+// Acumulación de un render largo, guardada tesela a tesela en path. Las teselas
+// terminadas esperan en memoria hasta que pasa interval (o hasta terminar o
+// cancelar) y se escriben en su sitio del archivo, así que la memoria no crece
+// con el tamaño de la imagen. La escena y la cámara no se comprueban: al
+// reanudar hay que repetir la misma orden, cambiando como mucho --samples
+pub struct Checkpoint {
+    path: String,
+    interval: Duration,
+    key: RenderKey,
+    file: Mutex<File>,
+    // Teselas terminadas que aún no se han escrito y hora del último guardado
+    pending: Mutex<(Vec<TileBlock>, Instant)>,
+}
+
+impl Checkpoint {
+    // Abre path si existe; si no, lo crea con todas las teselas sin muestras.
+    // samples son las muestras por píxel pedidas: un checkpoint con más no se
+    // recorta, así que se avisa de que esas teselas se quedan con todas
+    pub fn open(path: &str, interval: Duration, key: RenderKey, samples: u32) -> Result<Self, String> {
+        let exists = std::path::Path::new(path).exists();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|err| format!("No se pudo abrir el checkpoint {}: {}", path, err))?;
+        let mut checkpoint = Checkpoint {
+            path: path.to_string(),
+            interval,
+            key,
+            file: Mutex::new(file),
+            pending: Mutex::new((Vec::new(), Instant::now())),
+        };
+        
+        if exists {
+            let (saved, tiles, max_samples) = checkpoint.check()?;
+            println!("Checkpoint {} cargado: {} de {} teselas con muestras", path, saved, tiles);
+            if max_samples > samples {
+                println!(
+                    "Advertencia: el checkpoint tiene hasta {} muestras por píxel, más que las {} pedidas; se conservan todas",
+                    max_samples, samples
+                );
+            }
+        } else {
+            checkpoint.create().map_err(|err| format!("No se pudo crear el checkpoint {}: {}", path, err))?;
+        }
+        Ok(checkpoint)
+    }
+
+    // Estados de la tesela de la región con esquina (x, y), en orden de lectura.
+    // Una tesela sin guardar, o dañada por un corte a mitad de escritura, empieza de cero
+    pub fn tile(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<PixelState> {
+        let (offset, size) = self.block(x, y, width, height);
+        let mut bytes = vec![0u8; size];
+        let read = {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(offset)).and_then(|_| file.read_exact(&mut bytes))
+        };
+        match read.map(|()| decode_tile(&bytes)) {
+            Ok(Some(states)) => return states,
+            Ok(None) if bytes.iter().all(|&b| b == 0) => {}
+            _ => eprintln!("\nLa tesela ({}, {}) del checkpoint está dañada; se renderiza de nuevo", x, y),
+        }
+        let [rx, ry, _, _] = self.key.region;
+        (ry + y..ry + y + height)
+            .flat_map(|py| (rx + x..rx + x + width).map(move |px| PixelState::new(px, py, self.key.seed)))
+            .collect()
+    }
+
+    // Apunta el resultado de una tesela y guarda si ya toca. La escritura se hace
+    // sin bloquear a los hilos que terminan otras teselas
+    pub fn update(&self, x: u32, y: u32, width: u32, states: Vec<PixelState>) {
+        let height = states.len() as u32 / width;
+        let (offset, _) = self.block(x, y, width, height);
+        let block = encode_tile(&states);
+        let batch = {
+            let mut pending = self.pending.lock().unwrap();
+            let (blocks, last_save) = &mut *pending;
+            blocks.push((offset, block));
+            if last_save.elapsed() < self.interval {
+                return;
+            }
+            *last_save = Instant::now();
+            std::mem::take(blocks)
+        };
+        self.write(batch);
+    }
+
+    pub fn save(&self) {
+        let batch = std::mem::take(&mut self.pending.lock().unwrap().0);
+        self.write(batch);
+    }
+
+    fn write(&self, batch: Vec<TileBlock>) {
+        let result = {
+            let mut file = self.file.lock().unwrap();
+            batch
+                .iter()
+                .try_for_each(|(offset, block)| file.seek(SeekFrom::Start(*offset)).and_then(|_| file.write_all(block)))
+                .and_then(|()| file.sync_data())
+        };
+        match result {
+            Ok(()) => println!("\nCheckpoint guardado en {}", self.path),
+            Err(err) => eprintln!("\nError al guardar el checkpoint {}: {}", self.path, err),
+        }
+    }
+
+    // Posición y tamaño en el archivo del bloque de la tesela (x, y). Las teselas
+    // van en el orden de lectura de Region::tiles: antes de la fila de teselas que
+    // empieza en y hay y filas de píxeles, y antes de la tesela, x columnas de su
+    // misma altura. Cada bloque acaba con su suma de comprobación
+    fn block(&self, x: u32, y: u32, width: u32, height: u32) -> (u64, usize) {
+        let region_width = self.key.region[2];
+        let columns = region_width.div_ceil(TILE_SIZE) as u64;
+        let pixels_before = y as u64 * region_width as u64 + x as u64 * height as u64;
+        let tiles_before = (y / TILE_SIZE) as u64 * columns + (x / TILE_SIZE) as u64;
+        let offset = HEADER_SIZE + pixels_before * PIXEL_SIZE + tiles_before * 8;
+        (offset, (width * height) as usize * PIXEL_SIZE as usize + 8)
+    }
+
+    fn file_size(&self) -> u64 {
+        let [_, _, width, height] = self.key.region;
+        let tiles = (width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE)) as u64;
+        HEADER_SIZE + (width as u64 * height as u64) * PIXEL_SIZE + tiles * 8
+    }
+
+    // Formato: cabecera y los campos de RenderKey como u32 y, por tesela, sus
+    // píxeles y la suma de comprobación, todo little endian. Las teselas sin
+    // guardar son ceros, así que crear el archivo no escribe nada más
+    fn create(&mut self) -> std::io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        for field in self.key.fields() {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        let size = self.file_size();
+        let file = self.file.get_mut().unwrap();
+        file.set_len(size)?;
+        file.write_all(&bytes)?;
+        file.sync_data()
+    }
+
+    // Comprueba que el archivo es de este render y cuenta las teselas guardadas
+    // y el máximo de muestras por píxel entre ellas
+    fn check(&mut self) -> Result<(usize, usize, u32), String> {
+        let path = self.path.clone();
+        let damaged = |_| format!("El checkpoint {} está incompleto o dañado", path);
+        let expected_size = self.file_size();
+        let key = self.key;
+        let [_, _, width, height] = key.region;
+        let tile_blocks: Vec<(u64, usize)> = (0..height)
+            .step_by(TILE_SIZE as usize)
+            .flat_map(|y| (0..width).step_by(TILE_SIZE as usize).map(move |x| (x, y)))
+            .map(|(x, y)| self.block(x, y, TILE_SIZE.min(width - x), TILE_SIZE.min(height - y)))
+            .collect();
+        let file = self.file.get_mut().unwrap();
+        
+        let mut header = [0u8; HEADER_SIZE as usize];
+        file.read_exact(&mut header).map_err(damaged)?;
+        if &header[..8] != MAGIC {
+            return Err(format!("{} no es un checkpoint de render", path));
+        }
+        let fields: Vec<u32> = header[8..].chunks(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+        if fields != key.fields() {
+            return Err(format!(
+                "El checkpoint {} es de otro render (resolución, región, semilla o integrador distintos)",
+                path
+            ));
+        }
+        if file.metadata().map_err(damaged)?.len() != expected_size {
+            return Err(format!("El checkpoint {} está incompleto o dañado", path));
+        }
+        
+        // Basta con mirar la suma de cada tesela y las muestras de su primer píxel,
+        // que son las de toda la tesela; los bloques se leen al renderizarlas
+        let mut saved = 0;
+        let mut max_samples = 0;
+        for &(offset, size) in &tile_blocks {
+            let mut checksum = [0u8; 8];
+            file.seek(SeekFrom::Start(offset + size as u64 - 8)).map_err(damaged)?;
+            file.read_exact(&mut checksum).map_err(damaged)?;
+            if checksum == [0; 8] {
+                continue;
+            }
+            let mut samples = [0u8; 4];
+            file.seek(SeekFrom::Start(offset + 24)).map_err(damaged)?;
+            file.read_exact(&mut samples).map_err(damaged)?;
+            saved += 1;
+            max_samples = max_samples.max(u32::from_le_bytes(samples));
+        }
+        Ok((saved, tile_blocks.len(), max_samples))
+    }
+}
+
+fn encode_tile(states: &[PixelState]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(states.len() * PIXEL_SIZE as usize + 8);
+    for pixel in states {
+        for c in pixel.sum {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+        bytes.extend_from_slice(&pixel.samples.to_le_bytes());
+        bytes.extend_from_slice(&pixel.rng.state().to_le_bytes());
+    }
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+// None si la tesela no se llegó a guardar o no cuadra con su suma
+fn decode_tile(bytes: &[u8]) -> Option<Vec<PixelState>> {
+    let (pixels, checksum) = bytes.split_at(bytes.len() - 8);
+    let checksum = u64::from_le_bytes(checksum.try_into().unwrap());
+    if checksum == 0 || checksum != fnv1a(pixels) {
+        return None;
+    }
+    let states = pixels
+        .chunks(PIXEL_SIZE as usize)
+        .map(|bytes| {
+            let f64_at = |i: usize| f64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+            PixelState {
+                sum: [f64_at(0), f64_at(8), f64_at(16)],
+                samples: u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+                rng: Rng::new(u64::from_le_bytes(bytes[28..36].try_into().unwrap())),
+            }
+        })
+        .collect();
+    Some(states)
+}
+
+// FNV-1a de 64 bits: detecta los bloques a medio escribir si el programa se corta
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
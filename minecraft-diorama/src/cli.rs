@@ -0,0 +1,315 @@
+use crate::animation::Interpolation;
+use crate::aov::Aov;
+use crate::biome::Biome;
+use crate::camera::Projection;
+use crate::clip::ClipFormat;
+use crate::tonemap::ToneMapper;
+use std::str::FromStr;
+
+// Opciones del render offline: `cargo run --release -- render [opciones]`
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub output: String,
+    pub time_of_day: f64,
+    pub sky: String,
+    pub turbidity: f64,
+    pub sun_position: Option<(f64, f64)>,
+    pub environment: Option<String>,
+    pub env_rotation: f64,
+    pub env_exposure: f64,
+    pub integrator: String,
+    pub fog: Option<f64>,
+    pub water_fog: Option<f64>,
+    pub light_shafts: Option<f64>,
+    pub shaft_steps: u32,
+    pub filter: String,
+    pub anim_time: f64,
+    pub resource_pack: Option<String>,
+    pub biome: String,
+    pub glossy_samples: u32,
+    pub aperture: f64,
+    // None: autoenfoque en lo que se ve en el centro de la imagen
+    pub focus_distance: Option<f64>,
+    pub projection: String,
+    pub view_height: f64,
+    pub panorama: Option<String>,
+    pub panorama_origin: Option<(f64, f64, f64)>,
+    pub camera_path: Option<String>,
+    pub interpolation: String,
+    pub turntable: Option<f64>,
+    pub fps: f64,
+    pub timelapse: Option<f64>,
+    pub exposure: f64,
+    pub tonemap: String,
+    pub aovs: Vec<String>,
+    pub denoise: bool,
+    // x, y, ancho y alto en píxeles de la parte de la imagen que se renderiza
+    pub region: Option<(u32, u32, u32, u32)>,
+    pub seed: u32,
+    // Archivo con las muestras acumuladas, para reanudar renders largos
+    pub checkpoint: Option<String>,
+    // Segundos entre guardados del checkpoint
+    pub checkpoint_interval: f64,
+}
+
+impl RenderOptions {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = RenderOptions {
+            width: 1280,
+            height: 720,
+            samples: 4,
+            output: "render.png".to_string(),
+            time_of_day: 10.0,
+            sky: "gradient".to_string(),
+            turbidity: 3.0,
+            sun_position: None,
+            environment: None,
+            env_rotation: 0.0,
+            env_exposure: 0.0,
+            integrator: "whitted".to_string(),
+            fog: None,
+            water_fog: None,
+            light_shafts: None,
+            shaft_steps: 32,
+            filter: "nearest".to_string(),
+            anim_time: 0.0,
+            resource_pack: None,
+            biome: "plains".to_string(),
+            glossy_samples: 4,
+            aperture: 0.0,
+            focus_distance: None,
+            projection: "perspective".to_string(),
+            view_height: 12.0,
+            panorama: None,
+            panorama_origin: None,
+            camera_path: None,
+            interpolation: "catmull-rom".to_string(),
+            turntable: None,
+            fps: 24.0,
+            timelapse: None,
+            exposure: 0.0,
+            tonemap: "none".to_string(),
+            aovs: Vec::new(),
+            denoise: false,
+            region: None,
+            seed: 0,
+            checkpoint: None,
+            checkpoint_interval: 60.0,
+        };
+        let mut sun_elevation = None;
+        let mut sun_azimuth = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--width" => options.width = parse_value(arg, iter.next())?,
+                "--height" => options.height = parse_value(arg, iter.next())?,
+                "--samples" => options.samples = parse_value(arg, iter.next())?,
+                "--output" | "-o" => options.output = parse_value(arg, iter.next())?,
+                "--time" => options.time_of_day = parse_value(arg, iter.next())?,
+                "--sky" => options.sky = parse_value(arg, iter.next())?,
+                "--turbidity" => options.turbidity = parse_value(arg, iter.next())?,
+                "--sun-elevation" => sun_elevation = Some(parse_value(arg, iter.next())?),
+                "--sun-azimuth" => sun_azimuth = Some(parse_value(arg, iter.next())?),
+                "--environment" => options.environment = Some(parse_value(arg, iter.next())?),
+                "--env-rotation" => options.env_rotation = parse_value(arg, iter.next())?,
+                "--env-exposure" => options.env_exposure = parse_value(arg, iter.next())?,
+                "--integrator" => options.integrator = parse_value(arg, iter.next())?,
+                "--fog" => options.fog = Some(parse_value(arg, iter.next())?),
+                "--water-fog" => options.water_fog = Some(parse_value(arg, iter.next())?),
+                "--light-shafts" => options.light_shafts = Some(parse_value(arg, iter.next())?),
+                "--shaft-steps" => options.shaft_steps = parse_value(arg, iter.next())?,
+                "--filter" => options.filter = parse_value(arg, iter.next())?,
+                "--anim-time" => options.anim_time = parse_value(arg, iter.next())?,
+                "--resource-pack" => options.resource_pack = Some(parse_value(arg, iter.next())?),
+                "--biome" => options.biome = parse_value(arg, iter.next())?,
+                "--glossy-samples" => options.glossy_samples = parse_value(arg, iter.next())?,
+                "--projection" => options.projection = parse_value(arg, iter.next())?,
+                "--view-height" => options.view_height = parse_value(arg, iter.next())?,
+                "--panorama" => options.panorama = Some(parse_value(arg, iter.next())?),
+                "--panorama-origin" => {
+                    let value: String = parse_value(arg, iter.next())?;
+                    let coords: Vec<f64> = value
+                        .split(',')
+                        .map(|c| parse_value(arg, Some(&c.trim().to_string())))
+                        .collect::<Result<_, _>>()?;
+                    if coords.len() != 3 {
+                        return Err(format!("Valor inválido para {}: {}", arg, value));
+                    }
+                    options.panorama_origin = Some((coords[0], coords[1], coords[2]));
+                }
+                "--camera-path" => options.camera_path = Some(parse_value(arg, iter.next())?),
+                "--interpolation" => options.interpolation = parse_value(arg, iter.next())?,
+                "--turntable" => options.turntable = Some(parse_value(arg, iter.next())?),
+                "--fps" => options.fps = parse_value(arg, iter.next())?,
+                "--timelapse" => options.timelapse = Some(parse_value(arg, iter.next())?),
+                "--exposure" => options.exposure = parse_value(arg, iter.next())?,
+                "--tonemap" => options.tonemap = parse_value(arg, iter.next())?,
+                "--aov" => {
+                    let value: String = parse_value(arg, iter.next())?;
+                    for name in value.split(',') {
+                        let names = match name {
+                            "all" => Aov::ALL.iter().map(|aov| aov.name()).collect(),
+                            _ if Aov::from_name(name).is_some() => vec![name],
+                            _ => return Err(format!("Pase auxiliar desconocido: {}", name)),
+                        };
+                        for name in names {
+                            if !options.aovs.iter().any(|aov| aov == name) {
+                                options.aovs.push(name.to_string());
+                            }
+                        }
+                    }
+                }
+                "--denoise" => options.denoise = true,
+                "--seed" => options.seed = parse_value(arg, iter.next())?,
+                "--checkpoint" => options.checkpoint = Some(parse_value(arg, iter.next())?),
+                "--checkpoint-interval" => options.checkpoint_interval = parse_value(arg, iter.next())?,
+                "--region" => {
+                    let value: String = parse_value(arg, iter.next())?;
+                    let values: Vec<u32> = value
+                        .split(',')
+                        .map(|c| parse_value(arg, Some(&c.trim().to_string())))
+                        .collect::<Result<_, _>>()?;
+                    if values.len() != 4 {
+                        return Err(format!("Valor inválido para {}: {}", arg, value));
+                    }
+                    options.region = Some((values[0], values[1], values[2], values[3]));
+                }
+                "--aperture" => options.aperture = parse_value(arg, iter.next())?,
+                "--focus-distance" => {
+                    let value: String = parse_value(arg, iter.next())?;
+                    options.focus_distance = if value == "auto" {
+                        None
+                    } else {
+                        Some(parse_value(arg, Some(&value))?)
+                    };
+                }
+                _ => return Err(format!("Opción desconocida: {}", arg)),
+            }
+        }
+
+        if options.sky != "gradient" && options.sky != "preetham" {
+            return Err(format!("Modelo de cielo desconocido: {}", options.sky));
+        }
+        if options.integrator != "whitted" && options.integrator != "path" {
+            return Err(format!("Integrador desconocido: {}", options.integrator));
+        }
+        if !["nearest", "bilinear", "trilinear"].contains(&options.filter.as_str()) {
+            return Err(format!("Filtrado de texturas desconocido: {}", options.filter));
+        }
+        if Biome::from_name(&options.biome).is_none() {
+            return Err(format!("Bioma desconocido: {}", options.biome));
+        }
+        if Projection::from_name(&options.projection).is_none() {
+            return Err(format!("Proyección desconocida: {}", options.projection));
+        }
+        if let Some(panorama) = &options.panorama {
+            if panorama != "equirectangular" && panorama != "cubemap" {
+                return Err(format!("Tipo de panorama desconocido: {}", panorama));
+            }
+        }
+        if ToneMapper::from_name(&options.tonemap).is_none() {
+            return Err(format!("Curva de tonos desconocida: {}", options.tonemap));
+        }
+        if Interpolation::from_name(&options.interpolation).is_none() {
+            return Err(format!("Interpolación desconocida: {}", options.interpolation));
+        }
+        let durations = [options.turntable, options.timelapse];
+        if options.fps <= 0.0 || durations.iter().flatten().any(|&duration| duration <= 0.0) {
+            return Err("Los fotogramas por segundo y las duraciones de animación deben ser positivos".to_string());
+        }
+        if options.view_height <= 0.0 {
+            return Err("La altura de la vista ortográfica debe ser positiva".to_string());
+        }
+        
+        // Con solo uno de los dos ángulos, el otro toma un valor por defecto
+        if sun_elevation.is_some() || sun_azimuth.is_some() {
+            options.sun_position = Some((sun_elevation.unwrap_or(45.0), sun_azimuth.unwrap_or(135.0)));
+        }
+
+        if options.aperture < 0.0 || options.focus_distance.is_some_and(|d| d <= 0.0) {
+            return Err("La apertura no puede ser negativa y el enfoque debe ser positivo".to_string());
+        }
+
+        if options.width < 2 || options.height < 2 || options.samples == 0 {
+            return Err("La resolución debe ser al menos 2x2 y con 1 muestra o más".to_string());
+        }
+        let animated = options.camera_path.is_some() || options.turntable.is_some() || options.timelapse.is_some();
+        if options.checkpoint.is_some() && (animated || options.panorama.is_some()) {
+            return Err("El checkpoint solo se puede usar con imágenes fijas".to_string());
+        }
+        if options.checkpoint_interval <= 0.0 {
+            return Err("El intervalo del checkpoint debe ser positivo".to_string());
+        }
+        if !options.aovs.is_empty() && ClipFormat::from_path(&options.output).is_some() {
+            return Err("Los pases auxiliares no se pueden guardar en un .gif o .apng".to_string());
+        }
+        if let Some((x, y, width, height)) = options.region {
+            let outside = |start: u32, size: u32, limit: u32| start.checked_add(size).is_none_or(|end| end > limit);
+            if width == 0 || height == 0 || outside(x, width, options.width) || outside(y, height, options.height) {
+                return Err(format!("La región debe estar dentro de la imagen de {}x{}", options.width, options.height));
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Falta el valor para {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("Valor inválido para {}: {}", flag, value))
+}
+
+pub fn print_usage() {
+    println!("Uso: minecraft_diorama render [opciones]");
+    println!("  --width N       Ancho de la imagen (1280)");
+    println!("  --height N      Alto de la imagen (720)");
+    println!("  --samples N     Muestras por píxel (4)");
+    println!("  --output RUTA   Archivo de salida (render.png); .exr y .hdr guardan el color lineal HDR");
+    println!("  --time H        Hora del día en horas, 0-24 (10)");
+    println!("  --sky MODELO    Modelo de cielo: gradient o preetham (gradient)");
+    println!("  --turbidity T   Turbidez del cielo preetham, 2-10 (3)");
+    println!("  --sun-elevation G  Elevación fija del sol en grados (usa la hora si no se da)");
+    println!("  --sun-azimuth G    Azimut fijo del sol en grados desde el norte");
+    println!("  --environment RUTA Mapa de entorno equirectangular .hdr/.exr como cielo");
+    println!("  --env-rotation G   Rotación del mapa de entorno en grados (0)");
+    println!("  --env-exposure EV  Exposición del mapa de entorno en pasos (0)");
+    println!("  --integrator I  Algoritmo: whitted o path (whitted)");
+    println!("  --fog D         Niebla por distancia con densidad D (desactivada)");
+    println!("  --water-fog D   Niebla bajo el agua con densidad D, p. ej. 0.35 (desactivada)");
+    println!("  --light-shafts D   Rayos de luz volumétricos con densidad D (desactivados)");
+    println!("  --shaft-steps N    Pasos de integración de los rayos de luz (32)");
+    println!("  --filter F      Filtrado de texturas: nearest, bilinear o trilinear (nearest)");
+    println!("  --anim-time S   Instante de las texturas animadas en segundos (0)");
+    println!("  --resource-pack RUTA  Paquete de recursos de Minecraft (.zip o carpeta)");
+    println!("  --biome B       Bioma por defecto para el tinte: plains, forest, taiga, desert... (plains);");
+    println!("                  los bloques con [biome=B] o en las zonas de layers/biomes.cfg conservan el suyo");
+    println!("  --glossy-samples N  Rayos por reflejo rugoso en modo whitted (4)");
+    println!("  --projection P  Proyección: perspective, orthographic, isometric o dimetric (perspective)");
+    println!("  --view-height H Alto en bloques de la vista ortográfica (12)");
+    println!("  --panorama T    Panorama de 360°: equirectangular (2:1) o cubemap (seis caras de alto x alto)");
+    println!("  --panorama-origin X,Y,Z  Punto desde el que se toma el panorama (la posición de la cámara)");
+    println!("  --exposure EV   Exposición en pasos antes de la curva de tonos (0)");
+    println!("  --tonemap T     Curva de tonos: none, reinhard, aces o agx (none)");
+    println!("  --aov LISTA     Pases auxiliares separados por comas: depth, normal, albedo, face, material o all");
+    println!("                  Se guardan como capas si la salida es .exr, o como render_depth.png...");
+    println!("  --denoise       Quitar el ruido guiándose por el albedo, las normales y la profundidad");
+    println!("  --region X,Y,ANCHO,ALTO  Renderizar solo ese recorte de la imagen (entera)");
+    println!("  --seed N        Semilla del muestreo aleatorio (0)");
+    println!("  --checkpoint ARCHIVO  Guardar las muestras acumuladas y reanudar desde ellas si el archivo existe;");
+    println!("                  con más --samples se añaden muestras al render guardado");
+    println!("  --checkpoint-interval SEG  Segundos entre guardados del checkpoint (60)");
+    println!("  --camera-path RUTA  Anima la cámara con fotogramas clave (tiempo x y z mira_x mira_y mira_z fov)");
+    println!("  --interpolation I   Interpolación de la trayectoria: catmull-rom o bezier (catmull-rom)");
+    println!("  --turntable S   Anima una vuelta alrededor del diorama en S segundos");
+    println!("  --timelapse S   Anima un día completo del cielo en S segundos; con --camera-path o");
+    println!("                  --turntable, el día dura lo que su animación");
+    println!("  --fps F         Fotogramas por segundo de las animaciones (24)");
+    println!("                  Se guardan como render_0000.png..., o en un solo archivo si la salida es .gif o .apng");
+    println!("  --aperture A    Diámetro de la lente para profundidad de campo, 0 la desactiva (0)");
+    println!("  --focus-distance D  Distancia de enfoque, o auto para el centro de la imagen (auto)");
+}
@@ -0,0 +1,79 @@
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, RgbImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Duration;
+
+// Velocidad de la cuantización NeuQuant de la paleta GIF: 1 es la mejor calidad
+// y 30 la más rápida; 10 apenas se distingue de 1 en los renders
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+// Animación en un único archivo, elegida por la extensión de la salida
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClipFormat {
+    // Paleta de 256 colores por fotograma
+    Gif,
+    // PNG animado a color completo
+    Apng,
+}
+
+impl ClipFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "gif" => Some(ClipFormat::Gif),
+            "apng" => Some(ClipFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+// Codifica en bucle infinito a fps los fotogramas guardados en frame_paths. Se
+// leen de uno en uno, así que la memoria no crece con la duración
+pub fn save_clip(path: &str, frame_paths: &[String], fps: f64, format: ClipFormat) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("No se pudo crear {}: {}", path, err))?;
+    let writer = BufWriter::new(file);
+    match format {
+        ClipFormat::Gif => save_gif(writer, frame_paths, fps),
+        ClipFormat::Apng => save_apng(writer, frame_paths, fps),
+    }
+    .map_err(|err| format!("Error al codificar {}: {}", path, err))
+}
+
+fn load_frame(path: &str) -> Result<RgbImage, String> {
+    image::open(path)
+        .map(|img| img.to_rgb8())
+        .map_err(|err| format!("no se pudo leer el fotograma {}: {}", path, err))
+}
+
+fn save_gif(writer: BufWriter<File>, frame_paths: &[String], fps: f64) -> Result<(), String> {
+    let mut encoder = GifEncoder::new_with_speed(writer, GIF_QUANTIZATION_SPEED);
+    encoder.set_repeat(Repeat::Infinite).map_err(|err| err.to_string())?;
+    let delay = Delay::from_saturating_duration(Duration::from_secs_f64(1.0 / fps));
+    for path in frame_paths {
+        let rgba = DynamicImage::ImageRgb8(load_frame(path)?).into_rgba8();
+        encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay)).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+// El crate image solo decodifica APNG, así que se escribe con png directamente
+fn save_apng(writer: BufWriter<File>, frame_paths: &[String], fps: f64) -> Result<(), String> {
+    let (width, height) = match frame_paths.first() {
+        Some(path) => image::image_dimensions(path).map_err(|err| err.to_string())?,
+        None => (1, 1),
+    };
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frame_paths.len() as u32, 0).map_err(|err| err.to_string())?;
+    // Retardo de 100 / (100 * fps) segundos, que admite fps fraccionarios
+    let denominator = (fps * 100.0).round().clamp(1.0, u16::MAX as f64) as u16;
+    encoder.set_frame_delay(100, denominator).map_err(|err| err.to_string())?;
+
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    for path in frame_paths {
+        writer.write_image_data(load_frame(path)?.as_raw()).map_err(|err| err.to_string())?;
+    }
+    writer.finish().map_err(|err| err.to_string())
+}
@@ -1,12 +1,21 @@
 use crate::vector::Vec3;
 use crate::ray::Ray;
 use crate::material::Material;
+use crate::biome::Biome;
+use crate::blockstate::BlockState;
+use crate::model::{slab_intersect, PlacedModel};
 
 #[derive(Clone)]
 pub struct Cube {
     pub min: Vec3,
     pub max: Vec3,
     pub material: Material,
+    // Clima del bloque para el tinte de césped, hojas y agua
+    pub biome: Biome,
+    // Modelos del bloque; vacío si es un cubo completo
+    pub models: Vec<PlacedModel>,
+    // Propiedades del bloque (orientación, conexiones...) que eligen sus modelos
+    pub state: BlockState,
 }
 
 pub struct HitRecord {
@@ -16,7 +25,11 @@ pub struct HitRecord {
     pub face: usize,
     pub u: f64,
     pub v: f64,
+    // Direcciones en el mundo en las que crecen u y v (para los mapas de normales)
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
     pub material: Material,
+    pub biome: Biome,
 }
 
 impl Cube {
@@ -26,13 +39,39 @@ impl Cube {
             min: center - Vec3::new(half_size, half_size, half_size),
             max: center + Vec3::new(half_size, half_size, half_size),
             material,
+            biome: Biome::plains(),
+            models: Vec::new(),
+            state: BlockState::default(),
         }
     }
     
+    pub fn with_state(mut self, state: BlockState) -> Self {
+        self.state = state;
+        self
+    }
+    
+    // Ocupa todo el bloque, ya sea sin modelos o con un modelo de cubo completo
+    pub fn is_full_block(&self) -> bool {
+        match self.models.as_slice() {
+            [] => true,
+            [placed] => placed.model.is_full_cube(),
+            _ => false,
+        }
+    }
+    
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+    
     pub fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !self.models.is_empty() {
+            return self.intersect_models(ray, t_min, t_max);
+        }
+        
         let mut tmin = t_min;
         let mut tmax = t_max;
         let mut hit_face = 0;
+        let mut exit_face = 0;
         
         // Intersección con planos X
         let inv_d = 1.0 / ray.direction.x;
@@ -52,6 +91,7 @@ impl Cube {
         }
         if t1 < tmax {
             tmax = t1;
+            exit_face = face1;
         }
         
         if tmin > tmax {
@@ -76,6 +116,7 @@ impl Cube {
         }
         if t1 < tmax {
             tmax = t1;
+            exit_face = face1;
         }
         
         if tmin > tmax {
@@ -100,13 +141,15 @@ impl Cube {
         }
         if t1 < tmax {
             tmax = t1;
+            exit_face = face1;
         }
         
         if tmin > tmax {
             return None;
         }
         
-        let t = if tmin > t_min { tmin } else { tmax };
+        // Si el rayo parte desde dentro del cubo, el impacto es la cara de salida
+        let (t, hit_face) = if tmin > t_min { (tmin, hit_face) } else { (tmax, exit_face) };
         
         if t < t_min || t > t_max {
             return None;
@@ -115,6 +158,7 @@ impl Cube {
         let point = ray.at(t);
         let normal = self.get_normal(hit_face);
         let (u, v) = self.get_uv(point, hit_face);
+        let (tangent, bitangent) = self.get_uv_axes(hit_face);
         
         Some(HitRecord {
             point,
@@ -123,7 +167,37 @@ impl Cube {
             face: hit_face,
             u,
             v,
+            tangent,
+            bitangent,
             material: self.material.clone(),
+            biome: self.biome,
+        })
+    }
+    
+    // Impacto con los elementos de los modelos; la cara es la textura del elemento
+    fn intersect_models(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        slab_intersect(&self.min, &self.max, ray.origin, ray.direction, t_min, t_max)?;
+        
+        let mut closest = None;
+        let mut closest_t = t_max;
+        for model in &self.models {
+            if let Some(hit) = model.intersect(&self.min, ray, t_min, closest_t) {
+                closest_t = hit.t;
+                closest = Some(hit);
+            }
+        }
+        
+        closest.map(|hit| HitRecord {
+            point: ray.at(hit.t),
+            normal: hit.normal,
+            t: hit.t,
+            face: hit.texture,
+            u: hit.u / 16.0,
+            v: 1.0 - hit.v / 16.0,
+            tangent: hit.tangent,
+            bitangent: -hit.bitangent,
+            material: self.material.clone(),
+            biome: self.biome,
         })
     }
     
@@ -156,4 +230,19 @@ impl Cube {
             _ => (0.0, 0.0),
         }
     }
-}
\ No newline at end of file
+    
+    // Ejes de get_uv en el mundo: (dirección de u, dirección de v)
+    fn get_uv_axes(&self, face: usize) -> (Vec3, Vec3) {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        match face {
+            0 => (x, z),
+            1 => (x, -z),
+            2 => (x, y),
+            3 => (-x, y),
+            4 => (z, y),
+            _ => (-z, y),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::aov::{AovBuffers, AovSample};
+use crate::raytracer::Band;
+use image::{Rgb, Rgb32FImage};
+use rayon::prelude::*;
+
+// Filtro à-trous con pesos por bordes (Dammertz et al. 2010): cada pasada
+// aplica un núcleo B3 de 5x5 con los huecos el doble de anchos que la anterior
+const ITERATIONS: u32 = 5;
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+// Tolerancias de los pesos: diferencia de iluminación, de normal y de
+// profundidad relativa por píxel de distancia
+const SIGMA_COLOR: f32 = 0.6;
+const SIGMA_NORMAL: f32 = 0.2;
+const SIGMA_DEPTH: f32 = 0.02;
+// Por debajo de este albedo no se separa la textura de la iluminación
+const MIN_ALBEDO: f32 = 0.01;
+// Filas que alcanza el filtro a cada lado de un píxel: dos vecinos por pasada,
+// con los huecos de todas ellas
+const REACH: u32 = 2 * ((1 << ITERATIONS) - 1);
+// Filas que se filtran de una vez al quitar el ruido por franjas
+const CHUNK_ROWS: u32 = 128;
+
+// Quita el ruido de un render con pocas muestras. Se filtra la iluminación
+// (el color dividido entre el albedo) para no emborronar las texturas, y los
+// pesos evitan mezclar píxeles de caras, bloques o profundidades distintas:
+// los vecinos con otro índice de cara o de bloque no cuentan, y la normal y la
+// profundidad suavizan el resto. El cielo se deja como está.
+pub fn denoise(beauty: &Rgb32FImage, guides: &AovBuffers) -> Rgb32FImage {
+    let (width, height) = beauty.dimensions();
+    let albedo = |i: usize| guides.sample(i).albedo.map(|a| a.max(MIN_ALBEDO));
+    let mut light: Vec<[f32; 3]> = beauty
+        .pixels()
+        .enumerate()
+        .map(|(i, p)| {
+            let a = albedo(i);
+            [p[0] / a[0], p[1] / a[1], p[2] / a[2]]
+        })
+        .collect();
+
+    for iteration in 0..ITERATIONS {
+        let step = 1i32 << iteration;
+        // El color se vuelve más estricto en cada pasada, como en el artículo
+        let sigma_color = SIGMA_COLOR / (1 << iteration) as f32;
+        let input = &light;
+        light = (0..height as i32)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                (0..width as i32).map(move |x| filter_pixel(input, guides, (x, y), step, sigma_color))
+            })
+            .collect();
+    }
+
+    Rgb32FImage::from_fn(width, height, |x, y| {
+        let i = (y * width + x) as usize;
+        if guides.sample(i).face == 0 {
+            return *beauty.get_pixel(x, y);
+        }
+        let a = albedo(i);
+        let l = light[i];
+        Rgb([l[0] * a[0], l[1] * a[1], l[2] * a[2]])
+    })
+}
+
+// Una pasada del filtro sobre el píxel (x, y), con los vecinos a step píxeles
+fn filter_pixel(light: &[[f32; 3]], guides: &AovBuffers, (x, y): (i32, i32), step: i32, sigma_color: f32) -> [f32; 3] {
+    let (width, height) = (guides.width, guides.height);
+    let center = (y as u32 * width + x as u32) as usize;
+    let p = guides.sample(center);
+    if p.face == 0 {
+        return light[center];
+    }
+    let c = light[center];
+
+    let mut sum = [0.0f32; 3];
+    let mut total = 0.0f32;
+    for (j, kernel_y) in KERNEL.iter().enumerate() {
+        for (i, kernel_x) in KERNEL.iter().enumerate() {
+            let qx = x + (i as i32 - 2) * step;
+            let qy = y + (j as i32 - 2) * step;
+            if qx < 0 || qy < 0 || qx >= width as i32 || qy >= height as i32 {
+                continue;
+            }
+            let index = (qy as u32 * width + qx as u32) as usize;
+            // Un índice de cara o de bloque distinto es un borde aunque la normal y
+            // la profundidad coincidan (dos bloques contiguos en el mismo plano)
+            let q = guides.sample(index);
+            if q.face != p.face || q.material != p.material {
+                continue;
+            }
+            let lq = light[index];
+
+            let color_distance = (0..3).map(|k| (c[k] - lq[k]).powi(2)).sum::<f32>();
+            let normal_distance = (0..3).map(|k| (p.normal[k] - q.normal[k]).powi(2)).sum::<f32>();
+            let depth_distance = (p.depth - q.depth).abs() / (p.depth.max(1e-3) * step as f32);
+            let weight = kernel_x
+                * kernel_y
+                * (-color_distance / (sigma_color * sigma_color)).exp()
+                * (-normal_distance / (SIGMA_NORMAL * SIGMA_NORMAL)).exp()
+                * (-(depth_distance * depth_distance) / (SIGMA_DEPTH * SIGMA_DEPTH)).exp();
+
+            for k in 0..3 {
+                sum[k] += lq[k] * weight;
+            }
+            total += weight;
+        }
+    }
+
+    if total > 0.0 {
+        sum.map(|s| s / total)
+    } else {
+        c
+    }
+}
+
+// Quita el ruido a un render que llega por franjas, sin tener la imagen entera:
+// cada tramo se filtra con REACH filas de contexto por arriba y por abajo, que
+// son todas las que alcanza el filtro, así que el resultado es el mismo que
+// filtrando la imagen completa
+pub struct BandDenoiser {
+    width: u32,
+    // Filas guardadas, desde la fila first de la región
+    first: u32,
+    rows: u32,
+    color: Vec<f32>,
+    guides: Vec<AovSample>,
+    // Primera fila que aún no se ha devuelto
+    next: u32,
+}
+
+impl BandDenoiser {
+    pub fn new(width: u32) -> Self {
+        BandDenoiser { width, first: 0, rows: 0, color: Vec::new(), guides: Vec::new(), next: 0 }
+    }
+
+    // Añade la siguiente franja y devuelve las filas que ya tienen debajo todo
+    // el contexto que necesitan, si son bastantes
+    pub fn push(&mut self, band: Band) -> Option<Band> {
+        self.color.extend_from_slice(&band.color);
+        self.guides.extend_from_slice(&band.aovs);
+        self.rows += band.height;
+        let ready = (self.first + self.rows).saturating_sub(REACH);
+        (ready >= self.next + CHUNK_ROWS).then(|| self.filter(ready))
+    }
+
+    // Las filas que quedan, al terminar la imagen
+    pub fn finish(mut self) -> Option<Band> {
+        let end = self.first + self.rows;
+        (end > self.next).then(|| self.filter(end))
+    }
+
+    // Filtra y devuelve las filas de next a end, y olvida las que ya no hacen
+    // falta como contexto
+    fn filter(&mut self, end: u32) -> Band {
+        let width = self.width as usize;
+        let window_end = (end + REACH).min(self.first + self.rows);
+        let window_rows = window_end - self.first;
+        let pixels = width * window_rows as usize;
+        let beauty = Rgb32FImage::from_raw(self.width, window_rows, self.color[..pixels * 3].to_vec()).unwrap();
+        let guides = AovBuffers::new(self.width, window_rows, self.guides[..pixels].to_vec());
+        let denoised = denoise(&beauty, &guides).into_raw();
+
+        let rows = (self.next - self.first) as usize * width..(end - self.first) as usize * width;
+        let band = Band {
+            y: self.next,
+            height: end - self.next,
+            color: denoised[rows.start * 3..rows.end * 3].to_vec(),
+            aovs: self.guides[rows].to_vec(),
+        };
+
+        let keep = end.saturating_sub(REACH).max(self.first);
+        let dropped = (keep - self.first) as usize * width;
+        self.color.drain(..dropped * 3);
+        self.guides.drain(..dropped);
+        self.rows -= keep - self.first;
+        self.first = keep;
+        self.next = end;
+        band
+    }
+}
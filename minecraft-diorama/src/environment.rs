@@ -0,0 +1,209 @@
+use crate::rng::Rng;
+use crate::vector::Vec3;
+use image::codecs::hdr::HdrDecoder;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// Mapa de entorno equirectangular (.hdr / .exr) usado como cielo y como luz.
+// La u recorre el azimut desde el norte (-Z) hacia el este (+X) y la v va del
+// cenit (arriba de la imagen) al nadir.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+    rotation: f64,
+    scale: f64,
+    // Distribución 2D por luminancia para el muestreo por importancia
+    marginal_cdf: Vec<f64>,
+    conditional_cdf: Vec<f64>,
+    total_weight: f64,
+    // Aproximación de la iluminación para el modo Whitted (ambiente + direccional)
+    ambient: [f64; 3],
+    light_direction: Vec3,
+    light_color: [f64; 3],
+}
+
+impl EnvironmentMap {
+    // La rotación (grados) gira el mapa alrededor del eje Y;
+    // la exposición (pasos) escala la radiancia por 2^exposure
+    pub fn load(path: &str, rotation: f64, exposure: f64) -> Result<Self, String> {
+        let (width, height, pixels) = read_hdr_pixels(path)
+            .map_err(|err| format!("No se pudo cargar el mapa de entorno {}: {}", path, err))?;
+
+        let mut map = EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            scale: 2f64.powf(exposure),
+            marginal_cdf: Vec::new(),
+            conditional_cdf: Vec::new(),
+            total_weight: 0.0,
+            ambient: [0.0; 3],
+            light_direction: Vec3::new(0.0, 1.0, 0.0),
+            light_color: [0.0; 3],
+        };
+        map.build_distribution();
+        map.build_lighting();
+
+        println!("✓ Mapa de entorno cargado: {} ({}x{})", path, width, height);
+        Ok(map)
+    }
+
+    fn build_distribution(&mut self) {
+        let (w, h) = (self.width, self.height);
+        self.conditional_cdf = vec![0.0; h * (w + 1)];
+        self.marginal_cdf = vec![0.0; h + 1];
+
+        for j in 0..h {
+            let sin_theta = (PI * (j as f64 + 0.5) / h as f64).sin();
+            let row = j * (w + 1);
+            for i in 0..w {
+                let weight = luminance(self.texel(i, j)) * sin_theta;
+                self.conditional_cdf[row + i + 1] = self.conditional_cdf[row + i] + weight;
+            }
+            self.marginal_cdf[j + 1] = self.marginal_cdf[j] + self.conditional_cdf[row + w];
+        }
+
+        self.total_weight = self.marginal_cdf[h];
+    }
+
+    fn build_lighting(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let mut mean = [0.0; 3];
+        let mut lobes = [Vec3::zero(); 3];
+        let mut lobe_luminance = Vec3::zero();
+
+        for j in 0..h {
+            for i in 0..w {
+                let u = (i as f64 + 0.5) / w as f64;
+                let v = (j as f64 + 0.5) / h as f64;
+                let dir = self.uv_to_direction(u, v);
+                let solid_angle = (2.0 * PI / w as f64) * (PI / h as f64) * (PI * v).sin();
+                let radiance = self.texel(i, j);
+
+                for c in 0..3 {
+                    mean[c] += radiance[c] * solid_angle;
+                    lobes[c] = lobes[c] + dir * (radiance[c] * solid_angle);
+                }
+                lobe_luminance = lobe_luminance + dir * (luminance(radiance) * solid_angle);
+            }
+        }
+
+        // Banda 0 y 1 de armónicos esféricos: irradiancia ≈ media + (v·n) / 2π
+        self.light_direction = lobe_luminance.normalize();
+        for c in 0..3 {
+            self.ambient[c] = mean[c] / (4.0 * PI);
+            self.light_color[c] = (lobes[c].dot(&self.light_direction) / (2.0 * PI) / 0.6).max(0.0);
+        }
+    }
+
+    pub fn ambient(&self) -> [f64; 3] {
+        self.ambient
+    }
+
+    pub fn light(&self) -> (Vec3, [f64; 3]) {
+        (self.light_direction, self.light_color)
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> [f64; 3] {
+        let (u, v) = self.direction_to_uv(&direction.normalize());
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.texel(i, j)
+    }
+
+    // Devuelve una dirección muestreada según la luminancia del mapa y su pdf (sr⁻¹)
+    pub fn sample(&self, rng: &mut Rng) -> (Vec3, f64) {
+        if self.total_weight <= 0.0 {
+            // Mapa negro: muestreo uniforme de la esfera
+            let y = 1.0 - 2.0 * rng.next_f64();
+            let phi = 2.0 * PI * rng.next_f64();
+            let r = (1.0 - y * y).max(0.0).sqrt();
+            return (Vec3::new(r * phi.cos(), y, r * phi.sin()), 1.0 / (4.0 * PI));
+        }
+
+        let w = self.width;
+        let target = rng.next_f64() * self.total_weight;
+        let j = (self.marginal_cdf.partition_point(|&c| c <= target) - 1).min(self.height - 1);
+
+        let row = &self.conditional_cdf[j * (w + 1)..(j + 1) * (w + 1)];
+        let target = rng.next_f64() * row[w];
+        let i = (row.partition_point(|&c| c <= target) - 1).min(w - 1);
+
+        let u = (i as f64 + rng.next_f64()) / w as f64;
+        let v = (j as f64 + rng.next_f64()) / self.height as f64;
+        let direction = self.uv_to_direction(u, v);
+
+        (direction, self.pdf_texel(i, j, v))
+    }
+
+    pub fn pdf(&self, direction: &Vec3) -> f64 {
+        if self.total_weight <= 0.0 {
+            return 1.0 / (4.0 * PI);
+        }
+        let (u, v) = self.direction_to_uv(&direction.normalize());
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pdf_texel(i, j, v)
+    }
+
+    fn pdf_texel(&self, i: usize, j: usize, v: f64) -> f64 {
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let row = j * (self.width + 1);
+        let weight = self.conditional_cdf[row + i + 1] - self.conditional_cdf[row + i];
+        let pdf_uv = weight / self.total_weight * (self.width * self.height) as f64;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn texel(&self, i: usize, j: usize) -> [f64; 3] {
+        let p = self.pixels[j * self.width + i];
+        [
+            p[0] as f64 * self.scale,
+            p[1] as f64 * self.scale,
+            p[2] as f64 * self.scale,
+        ]
+    }
+
+    fn direction_to_uv(&self, dir: &Vec3) -> (f64, f64) {
+        let phi = dir.x.atan2(-dir.z) - self.rotation;
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = dir.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = u * 2.0 * PI + self.rotation;
+        let theta = v * PI;
+        Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+    }
+}
+
+// image::open convierte los .hdr a 8 bits, así que se leen con su decodificador
+fn read_hdr_pixels(path: &str) -> image::ImageResult<(usize, usize, Vec<[f32; 3]>)> {
+    let is_radiance = Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("hdr"))
+        .unwrap_or(false);
+
+    if is_radiance {
+        let reader = BufReader::new(File::open(path)?);
+        let decoder = HdrDecoder::new(reader)?;
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?.iter().map(|p| [p[0], p[1], p[2]]).collect();
+        Ok((metadata.width as usize, metadata.height as usize, pixels))
+    } else {
+        let image = image::open(path)?.into_rgb32f();
+        let pixels = image.pixels().map(|p| [p[0], p[1], p[2]]).collect();
+        Ok((image.width() as usize, image.height() as usize, pixels))
+    }
+}
+
+fn luminance(c: [f64; 3]) -> f64 {
+    0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+}
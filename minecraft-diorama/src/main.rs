@@ -5,23 +5,67 @@ mod material;
 mod cube;
 mod scene;
 mod raytracer;
+mod sky;
+mod preetham;
+mod environment;
+mod rng;
+mod atmosphere;
+mod cli;
+mod resource_pack;
+mod biome;
+mod model;
+mod bsdf;
+mod blockstate;
+mod animation;
+mod clip;
+mod tonemap;
+mod aov;
+mod denoise;
+mod checkpoint;
+mod progressive;
+mod output;
 
-use camera::Camera;
+use animation::{CameraAnimation, Interpolation};
+use aov::Aov;
+use camera::{Camera, Projection};
+use checkpoint::{Checkpoint, RenderKey};
+use clip::ClipFormat;
+use output::Output;
+use progressive::Accumulation;
+use tonemap::{ToneMapper, ToneMapping};
 use scene::Scene;
+use sky::{Sky, SkyModel};
+use environment::EnvironmentMap;
+use raytracer::{Integrator, Progress, Region, RenderMonitor, RenderSettings};
+use atmosphere::{DistanceFog, LightShafts, WaterFog};
+use material::FilterMode;
+use resource_pack::ResourcePack;
+use biome::Biome;
+use rng::Rng;
 use vector::Vec3;
 use winit::event::{Event, WindowEvent, ElementState, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
 use pixels::{Pixels, SurfaceTexture};
-use std::time::Instant;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use rayon::prelude::*;
 
+// En ortográfica los rayos salen de esta distancia por detrás de la posición,
+// para que no se corten los bloques que quedan entre medias
+const ORTHOGRAPHIC_BACKOFF: f64 = 50.0;
+
 struct CameraController {
     position: Vec3,
     yaw: f64,
     pitch: f64,
     speed: f64,
     sensitivity: f64,
+    projection: Projection,
+    // Alto en bloques de lo que se ve con proyección ortográfica
+    view_height: f64,
     
     forward: bool,
     backward: bool,
@@ -39,6 +83,8 @@ impl CameraController {
             pitch: -30.0,
             speed: 10.0,
             sensitivity: 1.0,
+            projection: Projection::Perspective,
+            view_height: 12.0,
             forward: false,
             backward: false,
             left: false,
@@ -48,6 +94,15 @@ impl CameraController {
         }
     }
     
+    // Las proyecciones con ángulos fijos miran en la diagonal más cercana
+    fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        if let Some(pitch) = projection.preset_pitch() {
+            self.yaw = ((self.yaw - 45.0) / 90.0).round() * 90.0 + 45.0;
+            self.pitch = pitch;
+        }
+    }
+    
     fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) {
         let pressed = state == ElementState::Pressed;
         match key {
@@ -61,6 +116,10 @@ impl CameraController {
         }
     }
     
+    fn is_moving(&self) -> bool {
+        self.forward || self.backward || self.left || self.right || self.up || self.down
+    }
+    
     fn update(&mut self, delta_time: f64) {
         let yaw_rad = self.yaw.to_radians();
         let pitch_rad = self.pitch.to_radians();
@@ -81,11 +140,21 @@ impl CameraController {
         
         let speed = self.speed * delta_time;
         
-        if self.forward {
-            self.position = self.position + forward * speed;
-        }
-        if self.backward {
-            self.position = self.position - forward * speed;
+        // En ortográfica avanzar no cambia la imagen, así que hace zoom
+        if self.projection.is_orthographic() {
+            if self.forward {
+                self.view_height *= (-0.1 * speed).exp();
+            }
+            if self.backward {
+                self.view_height *= (0.1 * speed).exp();
+            }
+        } else {
+            if self.forward {
+                self.position = self.position + forward * speed;
+            }
+            if self.backward {
+                self.position = self.position - forward * speed;
+            }
         }
         if self.right {
             self.position = self.position - right * speed;
@@ -107,19 +176,34 @@ impl CameraController {
         self.pitch = self.pitch.clamp(-89.0, 89.0);
     }
     
-    fn get_camera(&self, aspect_ratio: f64) -> Camera {
+    fn forward(&self) -> Vec3 {
         let yaw_rad = self.yaw.to_radians();
         let pitch_rad = self.pitch.to_radians();
         
-        let look_at = self.position + Vec3::new(
+        Vec3::new(
             yaw_rad.cos() * pitch_rad.cos(),
             pitch_rad.sin(),
             yaw_rad.sin() * pitch_rad.cos(),
-        );
+        )
+    }
+    
+    fn get_camera(&self, aspect_ratio: f64) -> Camera {
+        let forward = self.forward();
+        
+        if self.projection.is_orthographic() {
+            let look_from = self.position - forward * ORTHOGRAPHIC_BACKOFF;
+            return Camera::orthographic(
+                look_from,
+                self.position,
+                Vec3::new(0.0, 1.0, 0.0),
+                self.view_height,
+                aspect_ratio,
+            );
+        }
         
         Camera::new(
             self.position,
-            look_at,
+            self.position + forward,
             Vec3::new(0.0, 1.0, 0.0),
             60.0,
             aspect_ratio,
@@ -128,16 +212,35 @@ impl CameraController {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("render") {
+        render_offline(&args[1..]);
+        return;
+    }
+    
     println!("Iniciando diorama Minecraft interactivo...");
     println!("\nControles:");
-    println!("  W/A/S/D - Mover cámara");
+    println!("  W/A/S/D - Mover cámara (W/S hacen zoom en ortográfica)");
     println!("  Space - Subir");
     println!("  Shift - Bajar");
     println!("  Mouse - Rotar cámara (click izquierdo y arrastra)");
+    println!("  +/- - Acelerar/frenar el ciclo día/noche");
+    println!("  P - Pausar/reanudar el ciclo día/noche");
+    println!("  G - Activar/desactivar niebla por distancia");
+    println!("  U - Activar/desactivar niebla bajo el agua");
+    println!("  F - Cambiar el filtrado de texturas");
+    println!("  B - Cambiar el bioma (tinte de césped, hojas y agua)");
+    println!("  T - Cambiar la curva de tonos: none, reinhard, aces, agx");
+    println!("  [ / ] - Bajar/subir la exposición medio paso");
+    println!("  O - Cambiar la proyección: perspectiva, ortográfica, isométrica, dimétrica");
+    println!("  R - Acumular muestras con la cámara quieta (congela el tiempo)");
+    println!("  N - Quitar el ruido de la imagen acumulada");
     println!("  ESC - Salir");
     
     println!("\nCargando escena...");
-    let scene = Scene::from_layers("layers/");
+    let pack_path = args.iter().position(|arg| arg == "--resource-pack").and_then(|i| args.get(i + 1));
+    let pack = load_resource_pack(pack_path);
+    let mut scene = Scene::from_layers("layers/", &pack);
     println!("Bloques cargados: {}", scene.cubes.len());
     
     // Configuración de ventana
@@ -170,6 +273,14 @@ fn main() {
     let mut last_mouse_pos: Option<(f64, f64)> = None;
     let mut frame_count = 0;
     let mut fps_timer = Instant::now();
+    let mut time_speed = 0.25; // Horas del día por segundo real
+    let mut time_paused = false;
+    let mut filter = FilterMode::Nearest;
+    let mut biome = Biome::plains();
+    let mut tone_mapping = ToneMapping::new(0.0, ToneMapper::None);
+    // Some mientras se acumulan muestras
+    let mut accumulation: Option<Accumulation> = None;
+    let mut denoise = false;
     
     println!("\n¡Ventana abierta! Usa el mouse y teclado para navegar.");
     println!("Optimización: Paralelización Rayon activada");
@@ -184,30 +295,100 @@ fn main() {
                 }
                 WindowEvent::KeyboardInput { input, .. } => {
                     if let Some(keycode) = input.virtual_keycode {
-                        if keycode == VirtualKeyCode::Escape {
-                            *control_flow = ControlFlow::Exit;
-                        } else {
-                            controller.process_keyboard(keycode, input.state);
+                        let pressed = input.state == ElementState::Pressed;
+                        // Casi todas las teclas cambian la vista o la escena; la
+                        // exposición y la curva de tonos se aplican después
+                        let keeps_view = matches!(keycode, VirtualKeyCode::T | VirtualKeyCode::LBracket | VirtualKeyCode::RBracket | VirtualKeyCode::N);
+                        if let (true, false, Some(accumulation)) = (pressed, keeps_view, &mut accumulation) {
+                            accumulation.reset();
+                        }
+                        match keycode {
+                            VirtualKeyCode::Escape => *control_flow = ControlFlow::Exit,
+                            VirtualKeyCode::P if pressed => {
+                                time_paused = !time_paused;
+                                println!("Ciclo día/noche {}", if time_paused { "pausado" } else { "reanudado" });
+                            }
+                            VirtualKeyCode::G if pressed => {
+                                let atmosphere = &mut scene.atmosphere;
+                                atmosphere.fog = match atmosphere.fog {
+                                    Some(_) => None,
+                                    None => Some(DistanceFog::new(0.03)),
+                                };
+                                println!("Niebla por distancia: {}", if atmosphere.fog.is_some() { "activada" } else { "desactivada" });
+                            }
+                            VirtualKeyCode::U if pressed => {
+                                let atmosphere = &mut scene.atmosphere;
+                                atmosphere.water_fog = match atmosphere.water_fog {
+                                    Some(_) => None,
+                                    None => Some(WaterFog::new(0.35)),
+                                };
+                                println!("Niebla bajo el agua: {}", if atmosphere.water_fog.is_some() { "activada" } else { "desactivada" });
+                            }
+                            VirtualKeyCode::F if pressed => {
+                                filter = filter.next();
+                                scene.set_texture_filter(filter);
+                                println!("Filtrado de texturas: {}", filter.name());
+                            }
+                            VirtualKeyCode::T if pressed => {
+                                tone_mapping.mapper = tone_mapping.mapper.next();
+                                println!("Curva de tonos: {}", tone_mapping.mapper.name());
+                            }
+                            VirtualKeyCode::LBracket if pressed => {
+                                tone_mapping.exposure -= 0.5;
+                                println!("Exposición: {:+.1} EV", tone_mapping.exposure);
+                            }
+                            VirtualKeyCode::RBracket if pressed => {
+                                tone_mapping.exposure += 0.5;
+                                println!("Exposición: {:+.1} EV", tone_mapping.exposure);
+                            }
+                            VirtualKeyCode::R if pressed => {
+                                accumulation = match accumulation {
+                                    Some(_) => None,
+                                    None => Some(Accumulation::new(render_width, render_height)),
+                                };
+                                println!("Acumulación de muestras: {}", if accumulation.is_some() { "activada" } else { "desactivada" });
+                            }
+                            VirtualKeyCode::N if pressed => {
+                                denoise = !denoise;
+                                println!("Quitar ruido: {}", if denoise { "activado" } else { "desactivado" });
+                            }
+                            VirtualKeyCode::O if pressed => {
+                                controller.set_projection(controller.projection.next());
+                                println!("Proyección: {}", controller.projection.name());
+                            }
+                            VirtualKeyCode::B if pressed => {
+                                biome = biome.next();
+                                scene.set_biome(biome);
+                                println!("Bioma: {}", biome.name);
+                            }
+                            VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd if pressed => {
+                                time_speed = (time_speed * 2.0_f64).min(16.0);
+                                println!("Velocidad del ciclo: {:.3} h/s", time_speed);
+                            }
+                            VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract if pressed => {
+                                time_speed = (time_speed / 2.0_f64).max(1.0 / 64.0);
+                                println!("Velocidad del ciclo: {:.3} h/s", time_speed);
+                            }
+                            _ => controller.process_keyboard(keycode, input.state),
                         }
                     }
                 }
-                WindowEvent::MouseInput { state, button, .. } => {
-                    if button == winit::event::MouseButton::Left {
-                        mouse_grabbed = state == ElementState::Pressed;
-                        if !mouse_grabbed {
-                            last_mouse_pos = None;
-                        }
+                WindowEvent::MouseInput { state, button: winit::event::MouseButton::Left, .. } => {
+                    mouse_grabbed = state == ElementState::Pressed;
+                    if !mouse_grabbed {
+                        last_mouse_pos = None;
                     }
                 }
-                WindowEvent::CursorMoved { position, .. } => {
-                    if mouse_grabbed {
-                        if let Some((last_x, last_y)) = last_mouse_pos {
-                            let delta_x = position.x - last_x;
-                            let delta_y = position.y - last_y;
-                            controller.rotate(delta_x, delta_y);
+                WindowEvent::CursorMoved { position, .. } if mouse_grabbed => {
+                    if let Some((last_x, last_y)) = last_mouse_pos {
+                        let delta_x = position.x - last_x;
+                        let delta_y = position.y - last_y;
+                        controller.rotate(delta_x, delta_y);
+                        if let Some(accumulation) = &mut accumulation {
+                            accumulation.reset();
                         }
-                        last_mouse_pos = Some((position.x, position.y));
                     }
+                    last_mouse_pos = Some((position.x, position.y));
                 }
                 _ => {}
             },
@@ -218,10 +399,25 @@ fn main() {
                 
                 controller.update(delta_time);
                 
+                if let Some(accumulation) = &mut accumulation {
+                    if controller.is_moving() {
+                        accumulation.reset();
+                    }
+                } else {
+                    if !time_paused {
+                        scene.sky.advance(time_speed * delta_time);
+                    }
+                    scene.time += delta_time;
+                }
+                
                 // Contador de FPS
                 frame_count += 1;
                 if fps_timer.elapsed().as_secs() >= 1 {
-                    println!("FPS: {}", frame_count);
+                    let hours = scene.sky.time_of_day();
+                    match &accumulation {
+                        Some(accumulation) => println!("FPS: {} | Muestras: {}", frame_count, accumulation.samples),
+                        None => println!("FPS: {} | Hora: {:02}:{:02}", frame_count, hours as u32, (hours.fract() * 60.0) as u32),
+                    }
                     frame_count = 0;
                     fps_timer = Instant::now();
                 }
@@ -232,7 +428,17 @@ fn main() {
                 let aspect_ratio = render_width as f64 / render_height as f64;
                 let camera = controller.get_camera(aspect_ratio);
                 
-                render_to_pixels_parallel(&scene, &camera, pixels.frame_mut(), render_width, render_height);
+                match &mut accumulation {
+                    Some(accumulation) => {
+                        accumulation.add_sample(&scene, &camera);
+                        let img = accumulation.image(denoise);
+                        for (pixel, color) in pixels.frame_mut().chunks_exact_mut(4).zip(img.pixels()) {
+                            let [r, g, b] = tone_mapping.map_pixel([color[0] as f64, color[1] as f64, color[2] as f64]);
+                            pixel.copy_from_slice(&[r, g, b, 255]);
+                        }
+                    }
+                    None => render_to_pixels_parallel(&scene, &camera, &tone_mapping, pixels.frame_mut(), render_width, render_height),
+                }
                 
                 if let Err(err) = pixels.render() {
                     eprintln!("Error al renderizar: {}", err);
@@ -244,8 +450,299 @@ fn main() {
     });
 }
 
+// Render offline a archivo usando la misma cámara inicial del visor
+fn render_offline(args: &[String]) {
+    let options = match cli::RenderOptions::parse(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            cli::print_usage();
+            std::process::exit(1);
+        }
+    };
+    
+    let pack = load_resource_pack(options.resource_pack.as_ref());
+    let mut scene = Scene::from_layers("layers/", &pack);
+    let mut sky = Sky::new(options.time_of_day);
+    if options.sky == "preetham" {
+        sky = sky.with_model(SkyModel::Preetham { turbidity: options.turbidity });
+    }
+    if let Some((elevation, azimuth)) = options.sun_position {
+        sky = sky.with_sun_position(elevation, azimuth);
+    }
+    if let Some(path) = &options.environment {
+        match EnvironmentMap::load(path, options.env_rotation, options.env_exposure) {
+            Ok(map) => sky = sky.with_model(SkyModel::Environment(map)),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    scene.sky = sky;
+    
+    scene.atmosphere.fog = options.fog.map(DistanceFog::new);
+    scene.atmosphere.water_fog = options.water_fog.map(WaterFog::new);
+    scene.atmosphere.light_shafts = options.light_shafts.map(|density| LightShafts::new(density, options.shaft_steps));
+    
+    let filter = match options.filter.as_str() {
+        "bilinear" => FilterMode::Bilinear,
+        "trilinear" => FilterMode::Trilinear,
+        _ => FilterMode::Nearest,
+    };
+    scene.set_texture_filter(filter);
+    scene.time = options.anim_time;
+    scene.glossy_samples = options.glossy_samples;
+    if let Some(biome) = Biome::from_name(&options.biome) {
+        scene.set_biome(biome);
+    }
+    
+    let integrator = if options.integrator == "path" {
+        Integrator::PathTraced
+    } else {
+        Integrator::Whitted
+    };
+    let settings = RenderSettings {
+        samples: options.samples,
+        integrator,
+        denoise: options.denoise,
+        region: options.region.map(|(x, y, width, height)| Region::new(x, y, width, height)),
+        seed: options.seed,
+    };
+    let cancel = cancel_on_enter();
+    let monitor = RenderMonitor { on_progress: &print_progress, cancel: &cancel, checkpoint: None };
+    println!("Pulsa Enter para cancelar el render");
+    
+    let mut controller = CameraController::new(Vec3::new(6.0, 3.5, 6.0));
+    if let Some(projection) = Projection::from_name(&options.projection) {
+        controller.set_projection(projection);
+    }
+    controller.view_height = options.view_height;
+    
+    let mapper = ToneMapper::from_name(&options.tonemap).unwrap_or(ToneMapper::None);
+    let tone_mapping = ToneMapping::new(options.exposure, mapper);
+    let aovs: Vec<Aov> = options.aovs.iter().filter_map(|name| Aov::from_name(name)).collect();
+    let destination = Output { path: &options.output, aovs: &aovs, tone_mapping: &tone_mapping };
+    
+    let animation = if let Some(path) = &options.camera_path {
+        let interpolation = Interpolation::from_name(&options.interpolation).unwrap_or(Interpolation::CatmullRom);
+        match CameraAnimation::load(path, interpolation) {
+            Ok(animation) => Some(animation),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(duration) = options.turntable {
+        let (min, max) = scene.bounds();
+        Some(CameraAnimation::turntable(min, max, duration))
+    } else {
+        // Un timelapse sin trayectoria usa la cámara fija del render normal
+        options.timelapse.map(|duration| {
+            CameraAnimation::still(controller.position, controller.position + controller.forward(), 60.0, duration)
+        })
+    };
+    if let Some(animation) = animation {
+        render_animation(&mut scene, &animation, &options, &settings, &monitor, &destination);
+        return;
+    }
+    
+    if let Some(panorama) = &options.panorama {
+        let origin = options.panorama_origin.map_or(controller.position, |(x, y, z)| Vec3::new(x, y, z));
+        if panorama == "cubemap" {
+            let size = options.height;
+            for (face, camera) in Camera::cubemap(origin) {
+                let path = output::suffixed_path(&options.output, face);
+                if !output::render_to_file(&scene, &camera, size, size, &settings, &monitor, &Output { path: &path, ..destination }) {
+                    println!("Panorama cancelado en la cara {}", face);
+                    break;
+                }
+            }
+        } else {
+            let camera = Camera::equirectangular(origin);
+            output::render_to_file(&scene, &camera, options.width, options.height, &settings, &monitor, &destination);
+        }
+        return;
+    }
+    
+    let mut camera = controller.get_camera(options.width as f64 / options.height as f64);
+    if options.aperture > 0.0 && controller.projection.is_orthographic() {
+        println!("La profundidad de campo solo se aplica en perspectiva");
+    } else if options.aperture > 0.0 {
+        let focus_distance = options.focus_distance.unwrap_or_else(|| autofocus(&scene, &camera));
+        println!("Profundidad de campo: apertura {}, enfoque a {:.2}", options.aperture, focus_distance);
+        camera = camera.with_lens(options.aperture, focus_distance);
+    }
+    
+    // Solo el render fijo admite checkpoint (cli lo comprueba)
+    let checkpoint = options.checkpoint.as_ref().map(|path| {
+        let region = settings.region_within(options.width, options.height);
+        let key = RenderKey {
+            width: options.width,
+            height: options.height,
+            region: [region.x, region.y, region.width, region.height],
+            seed: settings.seed,
+            integrator: (integrator == Integrator::PathTraced) as u32,
+        };
+        match Checkpoint::open(path, Duration::from_secs_f64(options.checkpoint_interval), key, settings.samples) {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    });
+    let monitor = RenderMonitor { checkpoint: checkpoint.as_ref(), ..monitor };
+    
+    output::render_to_file(&scene, &camera, options.width, options.height, &settings, &monitor, &destination);
+}
+
+// Un PNG numerado por fotograma, o un único GIF/APNG si la salida tiene esa
+// extensión. Los PNG que ya existen se saltan, así que repetir la orden
+// continúa una animación interrumpida. Los fotogramas de un GIF/APNG van a la
+// carpeta <salida>.frames, que se codifica y se borra al terminar
+fn render_animation(
+    scene: &mut Scene,
+    animation: &CameraAnimation,
+    options: &cli::RenderOptions,
+    settings: &RenderSettings,
+    monitor: &RenderMonitor,
+    destination: &Output,
+) {
+    let frames = animation.frame_count(options.fps);
+    let aspect_ratio = options.width as f64 / options.height as f64;
+    let start_time = scene.time;
+    let clip_format = ClipFormat::from_path(&options.output);
+    let frames_dir = format!("{}.frames", options.output);
+    if clip_format.is_some() {
+        if let Err(err) = std::fs::create_dir_all(&frames_dir) {
+            eprintln!("Error: no se pudo crear {}: {}", frames_dir, err);
+            std::process::exit(1);
+        }
+    }
+    let mut clip_frames = Vec::new();
+    // Horas del día que avanza el cielo entre fotogramas: el día completo ocupa la
+    // animación que se renderiza, también si la marca una trayectoria o un turntable
+    let hours_per_frame = options.timelapse.map_or(0.0, |_| 24.0 / animation.frame_steps(options.fps) as f64);
+    println!("Animación de {} fotogramas a {} fps", frames, options.fps);
+    
+    for frame in 0..frames {
+        if frame > 0 && hours_per_frame > 0.0 {
+            scene.sky.advance(hours_per_frame);
+        }
+        let path = match clip_format {
+            Some(_) => format!("{}/{:04}.png", frames_dir, frame),
+            None => output::suffixed_path(&options.output, &format!("{:04}", frame)),
+        };
+        if clip_format.is_some() {
+            clip_frames.push(path.clone());
+        }
+        if std::path::Path::new(&path).exists() {
+            println!("{} ya existe, se salta", path);
+            continue;
+        }
+        
+        let time = frame as f64 / options.fps;
+        scene.time = start_time + time;
+        let mut camera = animation.camera_at(time, aspect_ratio);
+        // Sin distancia de enfoque, se enfoca el punto al que mira la cámara
+        if options.aperture > 0.0 {
+            let keyframe = animation.keyframe_at(time);
+            let focus_distance = options.focus_distance.unwrap_or_else(|| (keyframe.look_at - keyframe.position).length());
+            camera = camera.with_lens(options.aperture, focus_distance);
+        }
+        
+        println!("Fotograma {}/{}", frame + 1, frames);
+        // Un fotograma a medias no se guarda, para que al repetir la orden se renderice entero
+        if !output::render_to_file(scene, &camera, options.width, options.height, settings, monitor, &Output { path: &path, ..*destination }) {
+            println!("Animación cancelada en el fotograma {}", frame + 1);
+            return;
+        }
+    }
+    
+    if let Some(format) = clip_format {
+        match clip::save_clip(&options.output, &clip_frames, options.fps, format) {
+            Ok(()) => {
+                println!("Animación guardada en {}", options.output);
+                if let Err(err) = std::fs::remove_dir_all(&frames_dir) {
+                    eprintln!("No se pudo borrar {}: {}", frames_dir, err);
+                }
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// Enter en la terminal pide cancelar el render en curso. Si la entrada
+// estándar no es interactiva, read_line devuelve 0 y no se cancela nada
+fn cancel_on_enter() -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancel);
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        if matches!(std::io::stdin().read_line(&mut line), Ok(read) if read > 0) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    cancel
+}
+
+// Una línea que se reescribe cada vez que el render avanza un 1 %
+fn print_progress(progress: Progress) {
+    let percent = (progress.fraction() * 100.0) as u32;
+    let previous = ((progress.tiles_done - 1) as f64 / progress.tiles as f64 * 100.0) as u32;
+    let finished = progress.tiles_done == progress.tiles;
+    if percent == previous && !finished {
+        return;
+    }
+    let eta = progress.eta().map_or("--".to_string(), format_duration);
+    print!("\r  {:3}% | {} transcurrido | quedan {}   ", percent, format_duration(progress.elapsed), eta);
+    if finished {
+        println!();
+    }
+    std::io::stdout().flush().ok();
+}
+
+// 1h 02m 03s, 2m 05s o 12s
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
+
+// Distancia al primer bloque en el centro de la imagen; si no hay ninguno,
+// se enfoca a la distancia del origen de la escena
+fn autofocus(scene: &Scene, camera: &Camera) -> f64 {
+    let ray = camera.get_ray(0.5, 0.5);
+    match scene.intersect(&ray, 0.001, f64::INFINITY) {
+        Some(hit) => hit.t,
+        None => ray.origin.length(),
+    }
+}
+
+// Paquete de recursos elegido con --resource-pack, o las texturas de textures/
+fn load_resource_pack(path: Option<&String>) -> ResourcePack {
+    match path {
+        Some(path) => match ResourcePack::open(path) {
+            Ok(pack) => pack,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        },
+        None => ResourcePack::builtin(),
+    }
+}
+
 // Versión paralela del renderizado en tiempo real con MEJOR CALIDAD
-fn render_to_pixels_parallel(scene: &Scene, camera: &Camera, frame: &mut [u8], width: u32, height: u32) {
+fn render_to_pixels_parallel(scene: &Scene, camera: &Camera, tone_mapping: &ToneMapping, frame: &mut [u8], width: u32, height: u32) {
+    let (cone_width, spread) = camera.pixel_cone(height);
     let pixels: Vec<(usize, [u8; 4])> = (0..height)
         .into_par_iter()
         .flat_map(|y| {
@@ -253,13 +750,11 @@ fn render_to_pixels_parallel(scene: &Scene, camera: &Camera, frame: &mut [u8], w
                 let u = x as f64 / (width - 1) as f64;
                 let v = ((height - 1 - y) as f64) / (height - 1) as f64;
                 
-                let ray = camera.get_ray(u, v);
-                let color = raytracer::trace_ray(&ray, scene, 0);
+                let ray = camera.get_ray(u, v).with_cone(cone_width, spread);
+                let mut rng = Rng::for_pixel(x, y, 0);
+                let color = raytracer::trace_ray(&ray, scene, 0, &mut rng);
                 
-                // Gamma correction mejorada
-                let r = (color[0].clamp(0.0, 1.0).sqrt() * 255.0) as u8;
-                let g = (color[1].clamp(0.0, 1.0).sqrt() * 255.0) as u8;
-                let b = (color[2].clamp(0.0, 1.0).sqrt() * 255.0) as u8;
+                let [r, g, b] = tone_mapping.map_pixel(color);
                 
                 let idx = ((y * width + x) * 4) as usize;
                 (idx, [r, g, b, 255])
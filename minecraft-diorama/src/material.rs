@@ -1,66 +1,412 @@
-use image::{DynamicImage, GenericImageView};
-use std::path::Path;
+use crate::biome::{Biome, Colormap};
+use crate::resource_pack::ResourcePack;
+use crate::vector::Vec3;
+use image::RgbaImage;
+use std::fs;
+use std::sync::Arc;
+
+// Los téxeles con alfa por debajo de este valor se consideran huecos
+const ALPHA_CUTOFF: u8 = 128;
+
+// Las animaciones de Minecraft cuentan el tiempo en ticks de juego
+const TICKS_PER_SECOND: f64 = 20.0;
+
+// Reflectancia a incidencia normal de un dieléctrico corriente (piedra, madera)
+const DEFAULT_F0: f64 = 0.04;
+
+// En el canal verde del mapa _s de LabPBR, los valores desde 230 son metales
+const LABPBR_METAL: u8 = 230;
+
+// Filtrado de texturas al muestrear el color de una cara
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterMode {
+    // Vecino más cercano: píxeles nítidos en primeros planos
+    Nearest,
+    // Interpolación bilineal sobre la textura original
+    Bilinear,
+    // Bilineal entre los dos mipmaps más cercanos según el tamaño del píxel
+    Trilinear,
+}
+
+impl FilterMode {
+    pub fn next(self) -> Self {
+        match self {
+            FilterMode::Nearest => FilterMode::Bilinear,
+            FilterMode::Bilinear => FilterMode::Trilinear,
+            FilterMode::Trilinear => FilterMode::Nearest,
+        }
+    }
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterMode::Nearest => "nearest",
+            FilterMode::Bilinear => "bilinear",
+            FilterMode::Trilinear => "trilinear",
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Texture {
-    image: DynamicImage,
+    // Cadena de mipmaps precalculada por fotograma; el nivel 0 es la imagen original.
+    // Se comparte entre copias porque cada impacto clona el material.
+    frames: Arc<Vec<Vec<RgbaImage>>>,
+    animation: Option<Arc<Animation>>,
+    pub filter: FilterMode,
+    // Mapas LabPBR que acompañan a la textura (nombre_n.png y nombre_s.png)
+    normal_map: Option<Box<Texture>>,
+    specular_map: Option<Box<Texture>>,
+}
+
+// Animación de una tira vertical de fotogramas (convención .png.mcmeta)
+struct Animation {
+    // (índice del fotograma, duración en ticks)
+    frames: Vec<(usize, u32)>,
+    interpolate: bool,
+    total_ticks: u32,
+}
+
+impl Animation {
+    // Fotograma actual, el siguiente y la mezcla entre ambos en el instante time (s)
+    fn frame_at(&self, time: f64) -> (usize, usize, f64) {
+        let ticks = (time * TICKS_PER_SECOND).rem_euclid(self.total_ticks as f64);
+        let mut start = 0.0;
+        for (i, &(index, duration)) in self.frames.iter().enumerate() {
+            let end = start + duration as f64;
+            if ticks < end {
+                let next = self.frames[(i + 1) % self.frames.len()].0;
+                let blend = if self.interpolate { (ticks - start) / duration as f64 } else { 0.0 };
+                return (index, next, blend);
+            }
+            start = end;
+        }
+        let last = self.frames[self.frames.len() - 1].0;
+        (last, last, 0.0)
+    }
 }
 
 impl Texture {
     pub fn load(path: &str) -> Option<Self> {
-        if let Ok(img) = image::open(Path::new(path)) {
-            println!("✓ Textura cargada: {}", path);
-            Some(Texture { image: img })
-        } else {
-            println!("✗ Advertencia: No se pudo cargar textura: {}", path);
-            None
+        match fs::read(path) {
+            Ok(bytes) => {
+                let mcmeta = fs::read(format!("{}.mcmeta", path)).ok();
+                Texture::from_bytes(path, &bytes, mcmeta.as_deref())
+            }
+            Err(_) => {
+                println!("✗ Advertencia: No se pudo cargar textura: {}", path);
+                None
+            }
         }
     }
     
-    pub fn get_color(&self, u: f64, v: f64) -> [u8; 3] {
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0);
+    // Decodifica una textura ya leída (de disco o de un paquete de recursos)
+    // junto con su .png.mcmeta; label solo se usa en los mensajes
+    pub fn from_bytes(label: &str, bytes: &[u8], mcmeta: Option<&[u8]>) -> Option<Self> {
+        let img = match image::load_from_memory(bytes) {
+            Ok(img) => img,
+            Err(_) => {
+                println!("✗ Advertencia: No se pudo cargar textura: {}", label);
+                return None;
+            }
+        };
+        let mcmeta = mcmeta.and_then(|text| parse_mcmeta(label, text));
+        let texture = Texture::from_image(img.to_rgba8(), mcmeta);
+        match &texture.animation {
+            Some(_) => println!("✓ Textura animada cargada: {} ({} fotogramas)", label, texture.frames.len()),
+            None => println!("✓ Textura cargada: {}", label),
+        }
+        Some(texture)
+    }
+    
+    // Como en Minecraft, solo se anima si el .mcmeta tiene un objeto "animation":
+    // entonces la imagen es una tira de fotogramas cuadrados, salvo que indique
+    // otro tamaño de fotograma. Sin él, la imagen entera es un solo fotograma
+    fn from_image(image: RgbaImage, mcmeta: Option<serde_json::Value>) -> Self {
+        let animation_meta = mcmeta.as_ref().and_then(|meta| meta.get("animation")).filter(|anim| anim.is_object());
+        let meta_u32 = |key: &str| {
+            animation_meta
+                .and_then(|anim| anim.get(key))
+                .and_then(|value| value.as_u64())
+                .map(|value| value as u32)
+        };
+        
+        let frame_width = meta_u32("width").unwrap_or(image.width()).clamp(1, image.width());
+        let frame_height = meta_u32("height").unwrap_or(frame_width).clamp(1, image.height());
+        let count = (image.height() / frame_height).max(1);
+        if animation_meta.is_none() || count == 1 {
+            return Texture {
+                frames: Arc::new(vec![build_mip_chain(image)]),
+                animation: None,
+                filter: FilterMode::Nearest,
+                normal_map: None,
+                specular_map: None,
+            };
+        }
+        
+        let frames: Vec<Vec<RgbaImage>> = (0..count)
+            .map(|i| {
+                let frame = image::imageops::crop_imm(&image, 0, i * frame_height, frame_width, frame_height);
+                build_mip_chain(frame.to_image())
+            })
+            .collect();
         
-        let width = self.image.width();
-        let height = self.image.height();
+        // Orden y duración de los fotogramas: por defecto todos en orden
+        let frametime = meta_u32("frametime").unwrap_or(1).max(1);
+        let mut sequence: Vec<(usize, u32)> = animation_meta
+            .and_then(|anim| anim.get("frames"))
+            .and_then(|list| list.as_array())
+            .map(|list| {
+                list.iter()
+                    .filter_map(|entry| match entry {
+                        serde_json::Value::Object(obj) => {
+                            let index = obj.get("index")?.as_u64()? as usize;
+                            let time = obj.get("time").and_then(|t| t.as_u64()).map_or(frametime, |t| t as u32);
+                            Some((index, time.max(1)))
+                        }
+                        _ => Some((entry.as_u64()? as usize, frametime)),
+                    })
+                    .filter(|&(index, _)| index < frames.len())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if sequence.is_empty() {
+            sequence = (0..frames.len()).map(|i| (i, frametime)).collect();
+        }
         
-        let x = ((u * width as f64) as u32).min(width - 1);
-        let y = ((v * height as f64) as u32).min(height - 1);
+        let interpolate = animation_meta
+            .and_then(|anim| anim.get("interpolate"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        let total_ticks = sequence.iter().map(|&(_, ticks)| ticks).sum();
         
-        let pixel = self.image.get_pixel(x, y);
-        [pixel[0], pixel[1], pixel[2]]
+        Texture {
+            frames: Arc::new(frames),
+            animation: Some(Arc::new(Animation {
+                frames: sequence,
+                interpolate,
+                total_ticks,
+            })),
+            filter: FilterMode::Nearest,
+            normal_map: None,
+            specular_map: None,
+        }
+    }
+    
+    pub fn with_pbr_maps(mut self, normal_map: Option<Texture>, specular_map: Option<Texture>) -> Self {
+        self.normal_map = normal_map.map(Box::new);
+        self.specular_map = specular_map.map(Box::new);
+        self
+    }
+    
+    pub fn set_filter(&mut self, filter: FilterMode) {
+        self.filter = filter;
+        for map in self.normal_map.iter_mut().chain(self.specular_map.iter_mut()) {
+            map.set_filter(filter);
+        }
+    }
+    
+    // Téxel más cercano de la imagen original, sin filtrar
+    pub fn get_color(&self, u: f64, v: f64, time: f64) -> [u8; 4] {
+        self.blend_frames(time, |mips| nearest(&mips[0], u, v))
+    }
+    
+    // Color filtrado; footprint es el tamaño del píxel proyectado sobre la cara en UV
+    pub fn sample(&self, u: f64, v: f64, footprint: f64, time: f64) -> [u8; 4] {
+        self.blend_frames(time, |mips| match self.filter {
+            FilterMode::Nearest => nearest(&mips[0], u, v),
+            FilterMode::Bilinear => bilinear(&mips[0], u, v),
+            FilterMode::Trilinear => {
+                let base = &mips[0];
+                let max_level = (mips.len() - 1) as f64;
+                let texels = footprint * base.width().max(base.height()) as f64;
+                let lod = texels.max(1e-6).log2().clamp(0.0, max_level);
+                
+                let level = lod.floor() as usize;
+                let next = (level + 1).min(mips.len() - 1);
+                let a = bilinear(&mips[level], u, v);
+                let b = bilinear(&mips[next], u, v);
+                lerp4(a, b, lod - level as f64)
+            }
+        })
+    }
+    
+    // Evalúa el fotograma activo y, si la animación interpola, lo mezcla con el siguiente
+    fn blend_frames(&self, time: f64, eval: impl Fn(&[RgbaImage]) -> [f64; 4]) -> [u8; 4] {
+        let (current, next, blend) = match &self.animation {
+            Some(animation) => animation.frame_at(time),
+            None => (0, 0, 0.0),
+        };
+        let color = eval(&self.frames[current]);
+        if blend <= 0.0 {
+            return to_rgba8(color);
+        }
+        to_rgba8(lerp4(color, eval(&self.frames[next]), blend))
+    }
+}
+
+// Contenido de "<textura>.png.mcmeta"
+fn parse_mcmeta(label: &str, text: &[u8]) -> Option<serde_json::Value> {
+    match serde_json::from_slice(text) {
+        Ok(meta) => Some(meta),
+        Err(err) => {
+            println!("✗ Advertencia: .mcmeta inválido para {}: {}", label, err);
+            None
+        }
     }
 }
 
+// Reduce la imagen a la mitad (filtro de caja 2x2) hasta llegar a 1x1
+fn build_mip_chain(base: RgbaImage) -> Vec<RgbaImage> {
+    let mut mips = vec![base];
+    loop {
+        let prev = &mips[mips.len() - 1];
+        let (width, height) = (prev.width(), prev.height());
+        if width == 1 && height == 1 {
+            break;
+        }
+        let next_width = (width / 2).max(1);
+        let next_height = (height / 2).max(1);
+        let next = RgbaImage::from_fn(next_width, next_height, |x, y| {
+            let mut sum = [0u32; 4];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let px = prev.get_pixel((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+                for c in 0..4 {
+                    sum[c] += px[c] as u32;
+                }
+            }
+            image::Rgba(sum.map(|s| ((s + 2) / 4) as u8))
+        });
+        mips.push(next);
+    }
+    mips
+}
+
+// Interpolación bilineal con los bordes de la cara fijados (sin repetir)
+fn bilinear(image: &RgbaImage, u: f64, v: f64) -> [f64; 4] {
+    let (width, height) = (image.width(), image.height());
+    let x = u.clamp(0.0, 1.0) * width as f64 - 0.5;
+    let y = (1.0 - v.clamp(0.0, 1.0)) * height as f64 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    
+    let texel = |i: f64, j: f64| {
+        let px = image.get_pixel(i.clamp(0.0, (width - 1) as f64) as u32, j.clamp(0.0, (height - 1) as f64) as u32);
+        [px[0] as f64, px[1] as f64, px[2] as f64, px[3] as f64]
+    };
+    let (a, b) = (texel(x0, y0), texel(x0 + 1.0, y0));
+    let (c, d) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+    
+    let mut result = [0.0; 4];
+    for i in 0..4 {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        result[i] = top + (bottom - top) * fy;
+    }
+    result
+}
+
+fn nearest(image: &RgbaImage, u: f64, v: f64) -> [f64; 4] {
+    let u = u.clamp(0.0, 1.0);
+    let v = 1.0 - v.clamp(0.0, 1.0);
+    
+    let width = image.width();
+    let height = image.height();
+    
+    let x = ((u * width as f64) as u32).min(width - 1);
+    let y = ((v * height as f64) as u32).min(height - 1);
+    
+    image.get_pixel(x, y).0.map(|c| c as f64)
+}
+
+fn lerp4(a: [f64; 4], b: [f64; 4], t: f64) -> [f64; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+fn to_rgba8(color: [f64; 4]) -> [u8; 4] {
+    color.map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+// Tinte de bioma para las caras marcadas (el tintindex de los modelos de Minecraft)
+#[derive(Clone)]
+pub enum Tint {
+    Color([u8; 3]),
+    Colormap(Arc<Colormap>),
+}
+
+impl Tint {
+    pub fn color(&self, biome: &Biome) -> [u8; 3] {
+        match self {
+            Tint::Color(color) => *color,
+            Tint::Colormap(colormap) => colormap.lookup(biome),
+        }
+    }
+}
+
+// Parámetros del modelo metálico/rugosidad en un punto de una cara
+pub struct PbrSample {
+    // Rugosidad perceptual, como Material::roughness; el alfa de GGX es su cuadrado
+    pub roughness: f64,
+    pub metallic: f64,
+    // Reflectancia a incidencia normal de la parte no metálica
+    pub f0: f64,
+    // Normal del mapa en espacio tangente (x según u, y según v, z hacia fuera)
+    pub normal: Option<Vec3>,
+}
+
 #[derive(Clone)]
 pub struct Material {
-    pub textures: [Option<Texture>; 6],
+    pub name: String,
+    // Una textura por cara del cubo; los modelos JSON pueden usar más huecos
+    pub textures: Vec<Option<Texture>>,
     pub reflectivity: f64,
+    // Rugosidad perceptual (0 espejo, 1 mate) y metalicidad; los mapas _s los sustituyen
+    pub roughness: f64,
+    pub metallic: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    // Absorción de Beer–Lambert por unidad de distancia dentro del medio
+    pub absorption: [f64; 3],
+    // Prueba alfa: los rayos atraviesan los téxeles transparentes
+    pub alpha_cutout: bool,
+    pub tint: Option<Tint>,
+    pub tinted_faces: Vec<bool>,
+    // Capas que se dibujan sobre la textura de la cara (p. ej. el césped lateral)
+    pub overlays: Vec<Option<Texture>>,
     pub base_color: [u8; 3],
 }
 
 impl Material {
-    pub fn new(base_color: [u8; 3]) -> Self {
+    pub fn new(name: &str, base_color: [u8; 3]) -> Self {
         Material {
-            textures: [None, None, None, None, None, None],
+            name: name.to_string(),
+            textures: vec![None; 6],
             reflectivity: 0.0,
+            roughness: 1.0,
+            metallic: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            absorption: [0.0; 3],
+            alpha_cutout: false,
+            tint: None,
+            tinted_faces: vec![false; 6],
+            overlays: vec![None; 6],
             base_color,
         }
     }
     
-    pub fn with_texture(mut self, face: usize, texture_path: &str) -> Self {
+    pub fn with_texture(mut self, face: usize, texture: Option<Texture>) -> Self {
         if face < 6 {
-            self.textures[face] = Texture::load(texture_path);
+            self.textures[face] = texture;
         }
         self
     }
     
-    pub fn with_all_textures(mut self, texture_path: &str) -> Self {
-        let texture = Texture::load(texture_path);
+    pub fn with_all_textures(mut self, texture: Option<Texture>) -> Self {
         for i in 0..6 {
             self.textures[i] = texture.clone();
         }
@@ -72,67 +418,249 @@ impl Material {
         self
     }
     
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness;
+        self
+    }
+    
     pub fn with_transparency(mut self, transparency: f64, refractive_index: f64) -> Self {
         self.transparency = transparency;
         self.refractive_index = refractive_index;
         self
     }
     
-    pub fn get_color(&self, face: usize, u: f64, v: f64) -> [u8; 3] {
+    pub fn with_absorption(mut self, absorption: [f64; 3]) -> Self {
+        self.absorption = absorption;
+        self
+    }
+    
+    pub fn with_alpha_cutout(mut self) -> Self {
+        self.alpha_cutout = true;
+        self
+    }
+    
+    // Sin tinte (texturas ya coloreadas) las caras se dejan como están
+    pub fn with_tint(mut self, tint: Option<Tint>, faces: &[usize]) -> Self {
+        if tint.is_some() {
+            for &face in faces.iter().filter(|&&face| face < 6) {
+                self.tinted_faces[face] = true;
+            }
+        }
+        self.tint = tint;
+        self
+    }
+    
+    // Las capas superpuestas siempre se tiñen si el material tiene tinte
+    pub fn with_overlay(mut self, face: usize, overlay: Option<Texture>) -> Self {
         if face < 6 {
-            if let Some(ref texture) = self.textures[face] {
-                return texture.get_color(u, v);
+            self.overlays[face] = overlay;
+        }
+        self
+    }
+    
+    // Sustituye las texturas por los huecos de un modelo JSON (ver blockstate.rs)
+    pub fn set_texture_slots(&mut self, textures: Vec<Option<Texture>>, tinted: Vec<bool>, overlays: Vec<Option<Texture>>) {
+        self.textures = textures;
+        self.tinted_faces = tinted;
+        self.overlays = overlays;
+    }
+    
+    pub fn get_color(&self, face: usize, u: f64, v: f64, time: f64) -> [u8; 4] {
+        if let Some(Some(texture)) = self.textures.get(face) {
+            return texture.get_color(u, v, time);
+        }
+        let [r, g, b] = self.base_color;
+        [r, g, b, 255]
+    }
+    
+    // Color filtrado según el modo de la textura de la cara, con el tinte del bioma
+    pub fn sample(&self, face: usize, u: f64, v: f64, footprint: f64, time: f64, biome: &Biome) -> [u8; 4] {
+        let [r, g, b] = self.base_color;
+        let mut color = match self.textures.get(face) {
+            Some(Some(texture)) => texture.sample(u, v, footprint, time),
+            _ => [r, g, b, 255],
+        };
+        
+        let tint = self.tint.as_ref().map(|tint| tint.color(biome));
+        if let (Some(tint), Some(true)) = (tint, self.tinted_faces.get(face)) {
+            color = multiply(color, tint);
+        }
+        if let Some(Some(overlay)) = self.overlays.get(face) {
+            let mut layer = overlay.sample(u, v, footprint, time);
+            if let Some(tint) = tint {
+                layer = multiply(layer, tint);
+            }
+            let alpha = layer[3] as u32;
+            for c in 0..3 {
+                color[c] = ((layer[c] as u32 * alpha + color[c] as u32 * (255 - alpha)) / 255) as u8;
             }
         }
-        self.base_color
+        color
+    }
+    
+    // Parámetros PBR de la cara, leídos de los mapas LabPBR de su textura si los tiene.
+    // Del mapa _n solo se usa la normal (R y G, con la y hacia abajo como en DirectX);
+    // del _s, la suavidad perceptual (R, rugosidad = 1 - R) y la reflectancia o el indicador de metal (G).
+    pub fn pbr(&self, face: usize, u: f64, v: f64, footprint: f64, time: f64) -> PbrSample {
+        let mut sample = PbrSample {
+            roughness: self.roughness,
+            metallic: self.metallic,
+            f0: if self.reflectivity > 0.0 { self.reflectivity } else { DEFAULT_F0 },
+            normal: None,
+        };
+        let Some(Some(texture)) = self.textures.get(face) else {
+            return sample;
+        };
+        
+        if let Some(specular) = &texture.specular_map {
+            let [smoothness, reflectance, _, _] = specular.sample(u, v, footprint, time);
+            let smoothness = smoothness as f64 / 255.0;
+            sample.roughness = 1.0 - smoothness;
+            if reflectance >= LABPBR_METAL {
+                sample.metallic = 1.0;
+            } else {
+                sample.metallic = 0.0;
+                sample.f0 = reflectance as f64 / 255.0;
+            }
+        }
+        if let Some(normal_map) = &texture.normal_map {
+            let [r, g, _, _] = normal_map.sample(u, v, footprint, time);
+            let x = r as f64 / 255.0 * 2.0 - 1.0;
+            let y = g as f64 / 255.0 * 2.0 - 1.0;
+            let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+            sample.normal = Some(Vec3::new(x, -y, z).normalize());
+        }
+        sample
+    }
+    
+    // Reflejan el entorno los materiales con reflectividad, los metales y las
+    // caras con mapa _s; el resto solo tiene el brillo del sol
+    pub fn has_specular(&self, face: usize) -> bool {
+        let mapped = matches!(self.textures.get(face), Some(Some(texture)) if texture.specular_map.is_some());
+        self.reflectivity > 0.0 || self.metallic > 0.0 || mapped
+    }
+    
+    pub fn set_filter(&mut self, filter: FilterMode) {
+        for texture in self.textures.iter_mut().chain(self.overlays.iter_mut()).flatten() {
+            texture.set_filter(filter);
+        }
+    }
+    
+    // ¿El rayo atraviesa este punto de la cara por un hueco de la textura?
+    pub fn is_cutout(&self, face: usize, u: f64, v: f64, time: f64) -> bool {
+        self.alpha_cutout && self.get_color(face, u, v, time)[3] < ALPHA_CUTOFF
     }
 }
 
-pub fn create_stone_material() -> Material {
-    Material::new([128, 128, 128])
-        .with_all_textures("textures/stone.png")
+fn multiply(color: [u8; 4], tint: [u8; 3]) -> [u8; 4] {
+    [
+        (color[0] as u32 * tint[0] as u32 / 255) as u8,
+        (color[1] as u32 * tint[1] as u32 / 255) as u8,
+        (color[2] as u32 * tint[2] as u32 / 255) as u8,
+        color[3],
+    ]
 }
 
-pub fn create_dirt_material() -> Material {
-    Material::new([34, 139, 34])
-        .with_texture(0, "textures/grass_top.png")
-        .with_texture(1, "textures/dirt.png")
-        .with_texture(2, "textures/grass_side.png")
-        .with_texture(3, "textures/grass_side.png")
-        .with_texture(4, "textures/grass_side.png")
-        .with_texture(5, "textures/grass_side.png")
+pub fn create_stone_material(pack: &ResourcePack) -> Material {
+    Material::new("stone", [128, 128, 128])
+        .with_all_textures(pack.texture("stone"))
 }
 
-pub fn create_wood_material() -> Material {
-    Material::new([139, 90, 43])
-        // .with_all_textures("textures/wood.png")
+pub fn create_dirt_material(pack: &ResourcePack) -> Material {
+    let side = pack.texture("grass_block_side");
+    let overlay = pack.texture("grass_block_side_overlay");
+    Material::new("grass_block", [34, 139, 34])
+        .with_texture(0, pack.texture("grass_block_top"))
+        .with_texture(1, pack.texture("dirt"))
+        .with_texture(2, side.clone())
+        .with_texture(3, side.clone())
+        .with_texture(4, side.clone())
+        .with_texture(5, side)
+        .with_overlay(2, overlay.clone())
+        .with_overlay(3, overlay.clone())
+        .with_overlay(4, overlay.clone())
+        .with_overlay(5, overlay)
+        .with_tint(pack.grass_tint(), &[0])
 }
 
-pub fn create_leaves_material() -> Material {
-    Material::new([34, 139, 34])
-        .with_all_textures("textures/leaves.png")
-        .with_transparency(0.2, 1.0)
+pub fn create_wood_material(pack: &ResourcePack) -> Material {
+    let top = pack.texture("oak_log_top");
+    Material::new("oak_log", [139, 90, 43])
+        .with_all_textures(pack.texture("oak_log"))
+        .with_texture(0, top.clone())
+        .with_texture(1, top)
 }
 
-pub fn create_water_material() -> Material {
-    Material::new([30, 70, 200])
-        .with_all_textures("textures/water.png")
+pub fn create_leaves_material(pack: &ResourcePack) -> Material {
+    Material::new("oak_leaves", [34, 139, 34])
+        .with_all_textures(pack.texture("oak_leaves"))
+        .with_alpha_cutout()
+        .with_tint(pack.foliage_tint(), &[0, 1, 2, 3, 4, 5])
+}
+
+pub fn create_glass_material(pack: &ResourcePack) -> Material {
+    Material::new("glass", [220, 235, 240])
+        .with_all_textures(pack.texture("glass"))
+        .with_alpha_cutout()
+}
+
+pub fn create_water_material(pack: &ResourcePack) -> Material {
+    Material::new("water", [30, 70, 200])
+        .with_all_textures(pack.texture("water_still"))
+        .with_tint(pack.water_tint(), &[0, 1, 2, 3, 4, 5])
         .with_transparency(0.5, 1.33)
-        .with_reflectivity(0.1)
+        .with_absorption([0.45, 0.12, 0.06])
 }
 
-pub fn create_coal_ore_material() -> Material {
-    Material::new([64, 64, 64])
-        .with_all_textures("textures/coal_ore.png")
+pub fn create_coal_ore_material(pack: &ResourcePack) -> Material {
+    Material::new("coal_ore", [64, 64, 64])
+        .with_all_textures(pack.texture("coal_ore"))
+        .with_roughness(0.7)
 }
 
-pub fn create_iron_ore_material() -> Material {
-    Material::new([188, 152, 98])
-        .with_all_textures("textures/iron_ore.png")
+pub fn create_iron_ore_material(pack: &ResourcePack) -> Material {
+    Material::new("iron_ore", [188, 152, 98])
+        .with_all_textures(pack.texture("iron_ore"))
+        .with_roughness(0.45)
 }
 
-pub fn create_diamond_ore_material() -> Material {
-    Material::new([100, 200, 200])
-        .with_all_textures("textures/diamond_ore.png")
+pub fn create_diamond_ore_material(pack: &ResourcePack) -> Material {
+    Material::new("diamond_ore", [100, 200, 200])
+        .with_all_textures(pack.texture("diamond_ore"))
         .with_reflectivity(0.3)
-}
\ No newline at end of file
+        .with_roughness(0.15)
+}
+
+pub fn create_stone_slab_material(pack: &ResourcePack) -> Material {
+    Material::new("stone_slab", [128, 128, 128])
+        .with_all_textures(pack.texture("stone"))
+}
+
+pub fn create_stone_stairs_material(pack: &ResourcePack) -> Material {
+    Material::new("stone_stairs", [128, 128, 128])
+        .with_all_textures(pack.texture("stone"))
+}
+
+pub fn create_fence_material(pack: &ResourcePack) -> Material {
+    Material::new("oak_fence", [162, 130, 78])
+        .with_all_textures(pack.texture("oak_planks"))
+}
+
+pub fn create_torch_material(pack: &ResourcePack) -> Material {
+    Material::new("torch", [255, 200, 80])
+        .with_all_textures(pack.texture("torch"))
+        .with_alpha_cutout()
+}
+
+pub fn create_poppy_material(pack: &ResourcePack) -> Material {
+    Material::new("poppy", [200, 40, 30])
+        .with_all_textures(pack.texture("poppy"))
+        .with_alpha_cutout()
+}
+
+pub fn create_short_grass_material(pack: &ResourcePack) -> Material {
+    Material::new("short_grass", [100, 160, 60])
+        .with_all_textures(pack.texture("short_grass"))
+        .with_alpha_cutout()
+        .with_tint(pack.grass_tint(), &[0, 1, 2, 3, 4, 5])
+}
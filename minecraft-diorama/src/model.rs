@@ -0,0 +1,521 @@
+use crate::blockstate::BlockState;
+use crate::ray::Ray;
+use crate::vector::Vec3;
+use std::f64::consts::FRAC_PI_2;
+use std::sync::Arc;
+
+// Modelos de bloque que no son cubos completos, con la misma estructura que los
+// modelos JSON de Minecraft: cajas (elements) en píxeles de 0 a 16 dentro del bloque.
+// Una caja de grosor cero con una o dos caras hace de quad.
+
+// Cara de un elemento. Las UV van en píxeles con la v hacia abajo, como en Minecraft.
+#[derive(Clone, Copy)]
+pub struct ElementFace {
+    // Rectángulo [u1, v1, u2, v2]; si falta se deriva de la posición de la cara
+    pub uv: Option<[f64; 4]>,
+    // Índice de la textura del material que usa la cara
+    pub texture: usize,
+    // Giro de la textura dentro del rectángulo, en grados (0, 90, 180, 270)
+    pub rotation: i32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// Giro de un elemento alrededor de un eje que pasa por origin
+#[derive(Clone, Copy)]
+pub struct ElementRotation {
+    pub origin: Vec3,
+    pub axis: Axis,
+    pub angle: f64,
+    // Estira el elemento para que siga ocupando todo el bloque tras girar (plantas en cruz)
+    pub rescale: bool,
+}
+
+#[derive(Clone)]
+pub struct Element {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub rotation: Option<ElementRotation>,
+    // Caras en el orden de Cube: arriba, abajo, norte, sur, este, oeste
+    pub faces: [Option<ElementFace>; 6],
+}
+
+#[derive(Clone)]
+pub struct BlockModel {
+    pub elements: Vec<Element>,
+}
+
+// Modelo colocado en un bloque con la rotación de su variante (múltiplos de 90°,
+// como "x" e "y" en los blockstates). Un bloque puede tener varios (multipart).
+#[derive(Clone)]
+pub struct PlacedModel {
+    pub model: Arc<BlockModel>,
+    pub x_rotation: i32,
+    pub y_rotation: i32,
+}
+
+pub struct ModelHit {
+    pub t: f64,
+    pub normal: Vec3,
+    pub texture: usize,
+    pub u: f64,
+    pub v: f64,
+    // Direcciones en las que crecen u y v (v hacia abajo, en píxeles)
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+}
+
+impl Element {
+    // Caja con todas sus caras, cada una con la textura de su dirección
+    pub fn new(from: [f64; 3], to: [f64; 3]) -> Self {
+        let mut faces = [None; 6];
+        for (face, slot) in faces.iter_mut().enumerate() {
+            *slot = Some(ElementFace { uv: None, texture: face, rotation: 0 });
+        }
+        Element {
+            from: Vec3::new(from[0], from[1], from[2]),
+            to: Vec3::new(to[0], to[1], to[2]),
+            rotation: None,
+            faces,
+        }
+    }
+
+    // Deja solo las caras indicadas
+    pub fn with_faces(mut self, faces: &[usize]) -> Self {
+        for face in 0..6 {
+            if !faces.contains(&face) {
+                self.faces[face] = None;
+            }
+        }
+        self
+    }
+
+    pub fn with_uv(mut self, face: usize, uv: [f64; 4]) -> Self {
+        if let Some(element_face) = self.faces.get_mut(face).and_then(|f| f.as_mut()) {
+            element_face.uv = Some(uv);
+        }
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: ElementRotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    // Impacto con la cara más cercana posterior a t_min. El rayo está en el espacio del modelo.
+    fn intersect(&self, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> Option<ModelHit> {
+        let (origin, direction) = match &self.rotation {
+            Some(rotation) => (rotation.local_point(origin), rotation.local_vector(direction)),
+            None => (origin, direction),
+        };
+
+        let (t_entry, entry_face, t_exit, exit_face) = slab_intersect(&self.from, &self.to, origin, direction, t_min, t_max)?;
+        let candidates = [(t_entry, entry_face), (t_exit, exit_face)];
+        let (t, face, element_face) = candidates
+            .iter()
+            .filter(|&&(t, _)| t > t_min && t <= t_max)
+            .find_map(|&(t, face)| self.faces[face].map(|element_face| (t, face, element_face)))?;
+
+        let point = origin + direction * t;
+        let (u, v) = self.face_uv(face, &element_face, &point);
+        let (mut tangent, mut bitangent) = self.uv_axes(face, &element_face);
+        let mut normal = face_normal(face);
+        if let Some(rotation) = &self.rotation {
+            normal = rotation.model_normal(normal);
+            tangent = rotate_axis(tangent, rotation.axis, rotation.angle.to_radians());
+            bitangent = rotate_axis(bitangent, rotation.axis, rotation.angle.to_radians());
+        }
+
+        Some(ModelHit {
+            t,
+            normal,
+            texture: element_face.texture,
+            u,
+            v,
+            tangent,
+            bitangent,
+        })
+    }
+
+    // Direcciones de u y v de la textura sobre la cara, con el giro y la
+    // inversión del rectángulo uv que aplica face_uv
+    fn uv_axes(&self, face: usize, element_face: &ElementFace) -> (Vec3, Vec3) {
+        let (du, dv) = auto_uv_axes(face);
+        let (mut tangent, mut bitangent) = match element_face.rotation.rem_euclid(360) {
+            90 => (dv, -du),
+            180 => (-du, -dv),
+            270 => (-dv, du),
+            _ => (du, dv),
+        };
+        if let Some(uv) = element_face.uv {
+            if uv[2] < uv[0] {
+                tangent = -tangent;
+            }
+            if uv[3] < uv[1] {
+                bitangent = -bitangent;
+            }
+        }
+        (tangent, bitangent)
+    }
+
+    // UV en píxeles con la convención de Minecraft, mapeadas al rectángulo de la cara
+    fn face_uv(&self, face: usize, element_face: &ElementFace, point: &Vec3) -> (f64, f64) {
+        let (u, v) = auto_uv(face, point);
+        let (u_a, v_a) = auto_uv(face, &self.from);
+        let (u_b, v_b) = auto_uv(face, &self.to);
+        let uv = element_face.uv.unwrap_or([u_a.min(u_b), v_a.min(v_b), u_a.max(u_b), v_a.max(v_b)]);
+
+        let fraction = |value: f64, a: f64, b: f64| {
+            if (b - a).abs() < 1e-9 {
+                0.0
+            } else {
+                ((value - a.min(b)) / (b - a).abs()).clamp(0.0, 1.0)
+            }
+        };
+        let fu = fraction(u, u_a, u_b);
+        let fv = fraction(v, v_a, v_b);
+        // La textura gira en sentido horario sobre la cara
+        let (fu, fv) = match element_face.rotation.rem_euclid(360) {
+            90 => (fv, 1.0 - fu),
+            180 => (1.0 - fu, 1.0 - fv),
+            270 => (1.0 - fv, fu),
+            _ => (fu, fv),
+        };
+        (uv[0] + (uv[2] - uv[0]) * fu, uv[1] + (uv[3] - uv[1]) * fv)
+    }
+}
+
+impl ElementRotation {
+    fn scale(&self) -> Vec3 {
+        let s = if self.rescale { 1.0 / self.angle.to_radians().cos() } else { 1.0 };
+        match self.axis {
+            Axis::X => Vec3::new(1.0, s, s),
+            Axis::Y => Vec3::new(s, 1.0, s),
+            Axis::Z => Vec3::new(s, s, 1.0),
+        }
+    }
+
+    // Deshace el giro (y el reescalado) para pasar al espacio del elemento
+    fn local_point(&self, point: Vec3) -> Vec3 {
+        self.origin + self.local_vector(point - self.origin)
+    }
+
+    fn local_vector(&self, vector: Vec3) -> Vec3 {
+        let rotated = rotate_axis(vector, self.axis, -self.angle.to_radians());
+        let scale = self.scale();
+        Vec3::new(rotated.x / scale.x, rotated.y / scale.y, rotated.z / scale.z)
+    }
+
+    // Las normales se transforman con la inversa traspuesta del reescalado
+    fn model_normal(&self, normal: Vec3) -> Vec3 {
+        let scale = self.scale();
+        let scaled = Vec3::new(normal.x / scale.x, normal.y / scale.y, normal.z / scale.z);
+        rotate_axis(scaled, self.axis, self.angle.to_radians()).normalize()
+    }
+}
+
+impl BlockModel {
+    pub fn new(elements: Vec<Element>) -> Self {
+        BlockModel { elements }
+    }
+
+    // Un único elemento sin girar que ocupa todo el bloque
+    pub fn is_full_cube(&self) -> bool {
+        match self.elements.as_slice() {
+            [element] => {
+                element.rotation.is_none()
+                    && element.faces.iter().all(Option::is_some)
+                    && [element.from.x, element.from.y, element.from.z].iter().all(|c| c.abs() < 1e-6)
+                    && [element.to.x, element.to.y, element.to.z].iter().all(|c| (c - 16.0).abs() < 1e-6)
+            }
+            _ => false,
+        }
+    }
+
+    // Cubo completo; solo hace falta para girar las texturas (troncos tumbados)
+    pub fn cube() -> Self {
+        BlockModel::new(vec![Element::new([0.0, 0.0, 0.0], [16.0, 16.0, 16.0])])
+    }
+
+    // Losa inferior
+    pub fn slab() -> Self {
+        BlockModel::new(vec![Element::new([0.0, 0.0, 0.0], [16.0, 8.0, 16.0])])
+    }
+
+    // Escalera mirando al este, como el modelo vanilla sin rotar
+    pub fn stairs() -> Self {
+        BlockModel::new(vec![
+            Element::new([0.0, 0.0, 0.0], [16.0, 8.0, 16.0]),
+            Element::new([8.0, 8.0, 0.0], [16.0, 16.0, 16.0]),
+        ])
+    }
+
+    pub fn fence_post() -> Self {
+        BlockModel::new(vec![Element::new([6.0, 0.0, 6.0], [10.0, 16.0, 10.0])])
+    }
+
+    // Los dos travesaños de la valla hacia el norte; se giran para las demás direcciones
+    pub fn fence_side() -> Self {
+        BlockModel::new(vec![
+            Element::new([7.0, 12.0, 0.0], [9.0, 15.0, 9.0]),
+            Element::new([7.0, 6.0, 0.0], [9.0, 9.0, 9.0]),
+        ])
+    }
+
+    pub fn torch() -> Self {
+        BlockModel::new(vec![Element::new([7.0, 0.0, 7.0], [9.0, 10.0, 9.0])
+            .with_faces(&[0, 2, 3, 4, 5])
+            .with_uv(0, [7.0, 6.0, 9.0, 8.0])
+            .with_uv(2, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(3, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(4, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(5, [7.0, 6.0, 9.0, 16.0])])
+    }
+
+    // Antorcha apoyada en una pared al oeste, inclinada hacia el este
+    pub fn wall_torch() -> Self {
+        BlockModel::new(vec![Element::new([-1.0, 3.5, 7.0], [1.0, 13.5, 9.0])
+            .with_uv(0, [7.0, 6.0, 9.0, 8.0])
+            .with_uv(1, [7.0, 13.0, 9.0, 15.0])
+            .with_uv(2, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(3, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(4, [7.0, 6.0, 9.0, 16.0])
+            .with_uv(5, [7.0, 6.0, 9.0, 16.0])
+            .with_rotation(ElementRotation {
+                origin: Vec3::new(0.0, 3.5, 8.0),
+                axis: Axis::Z,
+                angle: -22.5,
+                rescale: false,
+            })])
+    }
+
+    // Dos planos cruzados a 45° (flores, hierba)
+    pub fn cross() -> Self {
+        let rotation = ElementRotation {
+            origin: Vec3::new(8.0, 8.0, 8.0),
+            axis: Axis::Y,
+            angle: 45.0,
+            rescale: true,
+        };
+        BlockModel::new(vec![
+            Element::new([0.8, 0.0, 8.0], [15.2, 16.0, 8.0])
+                .with_faces(&[2, 3])
+                .with_rotation(rotation),
+            Element::new([8.0, 0.0, 0.8], [8.0, 16.0, 15.2])
+                .with_faces(&[4, 5])
+                .with_rotation(rotation),
+        ])
+    }
+}
+
+impl PlacedModel {
+    pub fn new(model: Arc<BlockModel>, x_rotation: i32, y_rotation: i32) -> Self {
+        PlacedModel {
+            model,
+            x_rotation: x_rotation.rem_euclid(360) / 90,
+            y_rotation: y_rotation.rem_euclid(360) / 90,
+        }
+    }
+
+    // block_min es la esquina del bloque en el mundo; t es la misma que en el rayo del mundo
+    pub fn intersect(&self, block_min: &Vec3, ray: &Ray, t_min: f64, t_max: f64) -> Option<ModelHit> {
+        // Mundo -> píxeles del bloque -> espacio del modelo sin la rotación de la variante
+        let center = Vec3::new(8.0, 8.0, 8.0);
+        let origin = (ray.origin - *block_min) * 16.0 - center;
+        let direction = ray.direction * 16.0;
+        let origin = self.to_model(origin) + center;
+        let direction = self.to_model(direction);
+
+        let mut closest: Option<ModelHit> = None;
+        let mut closest_t = t_max;
+        for element in &self.model.elements {
+            if let Some(hit) = element.intersect(origin, direction, t_min, closest_t) {
+                closest_t = hit.t;
+                closest = Some(hit);
+            }
+        }
+
+        closest.map(|mut hit| {
+            hit.normal = self.to_world(hit.normal);
+            hit.tangent = self.to_world(hit.tangent);
+            hit.bitangent = self.to_world(hit.bitangent);
+            hit
+        })
+    }
+
+    // La variante gira primero en X y luego en Y; se deshace en orden inverso
+    fn to_model(&self, vector: Vec3) -> Vec3 {
+        let mut v = vector;
+        for _ in 0..(4 - self.y_rotation) % 4 {
+            v = rotate_y_quarter(v);
+        }
+        for _ in 0..(4 - self.x_rotation) % 4 {
+            v = rotate_x_quarter(v);
+        }
+        v
+    }
+
+    fn to_world(&self, vector: Vec3) -> Vec3 {
+        let mut v = vector;
+        for _ in 0..self.x_rotation {
+            v = rotate_x_quarter(v);
+        }
+        for _ in 0..self.y_rotation {
+            v = rotate_y_quarter(v);
+        }
+        v
+    }
+}
+
+// Modelos incluidos para los bloques que no son cubos, cuando el paquete de recursos
+// no trae sus JSON. Vacío para los cubos completos.
+pub fn builtin_models(block: &str, state: &BlockState) -> Vec<PlacedModel> {
+    let placed = |model: BlockModel, x: i32, y: i32| PlacedModel::new(Arc::new(model), x, y);
+    let top_half = if state.get("half") == Some("top") { 180 } else { 0 };
+    let facing = facing_rotation(state.get("facing"));
+
+    match block {
+        // Como en vanilla, el tronco tumbado es el vertical girado
+        "oak_log" => match state.get("axis") {
+            Some("x") => vec![placed(BlockModel::cube(), 90, 90)],
+            Some("z") => vec![placed(BlockModel::cube(), 90, 0)],
+            _ => Vec::new(),
+        },
+        "stone_slab" => match state.get("type") {
+            Some("double") => Vec::new(),
+            Some("top") => vec![placed(BlockModel::slab(), 180, 0)],
+            _ => vec![placed(BlockModel::slab(), 0, 0)],
+        },
+        "stone_stairs" => vec![placed(BlockModel::stairs(), top_half, facing)],
+        "oak_fence" => {
+            let side = Arc::new(BlockModel::fence_side());
+            let mut models = vec![placed(BlockModel::fence_post(), 0, 0)];
+            for (direction, y) in [("north", 0), ("east", 90), ("south", 180), ("west", 270)] {
+                if state.get(direction) == Some("true") {
+                    models.push(PlacedModel::new(side.clone(), 0, y));
+                }
+            }
+            models
+        }
+        "torch" => vec![placed(BlockModel::torch(), 0, 0)],
+        "wall_torch" => vec![placed(BlockModel::wall_torch(), 0, facing)],
+        "poppy" | "short_grass" => vec![placed(BlockModel::cross(), 0, 0)],
+        _ => Vec::new(),
+    }
+}
+
+// Giro en Y de los modelos orientados, que sin rotar miran al este
+fn facing_rotation(facing: Option<&str>) -> i32 {
+    match facing {
+        Some("south") => 90,
+        Some("west") => 180,
+        Some("north") => 270,
+        _ => 0,
+    }
+}
+
+// Giro de 90° en Y en sentido horario visto desde arriba: norte -> este
+fn rotate_y_quarter(v: Vec3) -> Vec3 {
+    rotate_axis(v, Axis::Y, -FRAC_PI_2)
+}
+
+// Giro de 90° en X: norte -> abajo, arriba -> norte
+fn rotate_x_quarter(v: Vec3) -> Vec3 {
+    rotate_axis(v, Axis::X, -FRAC_PI_2)
+}
+
+fn rotate_axis(v: Vec3, axis: Axis, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    match axis {
+        Axis::X => Vec3::new(v.x, v.y * cos - v.z * sin, v.y * sin + v.z * cos),
+        Axis::Y => Vec3::new(v.x * cos + v.z * sin, v.y, -v.x * sin + v.z * cos),
+        Axis::Z => Vec3::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos, v.z),
+    }
+}
+
+fn face_normal(face: usize) -> Vec3 {
+    match face {
+        0 => Vec3::new(0.0, 1.0, 0.0),
+        1 => Vec3::new(0.0, -1.0, 0.0),
+        2 => Vec3::new(0.0, 0.0, -1.0),
+        3 => Vec3::new(0.0, 0.0, 1.0),
+        4 => Vec3::new(1.0, 0.0, 0.0),
+        _ => Vec3::new(-1.0, 0.0, 0.0),
+    }
+}
+
+// UV automáticas de Minecraft para un punto de la cara (píxeles, v hacia abajo)
+fn auto_uv(face: usize, p: &Vec3) -> (f64, f64) {
+    match face {
+        0 => (p.x, p.z),
+        1 => (p.x, 16.0 - p.z),
+        2 => (16.0 - p.x, 16.0 - p.y),
+        3 => (p.x, 16.0 - p.y),
+        4 => (16.0 - p.z, 16.0 - p.y),
+        _ => (p.z, 16.0 - p.y),
+    }
+}
+
+// Direcciones en las que crecen las UV automáticas de cada cara
+fn auto_uv_axes(face: usize) -> (Vec3, Vec3) {
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    let z = Vec3::new(0.0, 0.0, 1.0);
+    match face {
+        0 => (x, z),
+        1 => (x, -z),
+        2 => (-x, -y),
+        3 => (x, -y),
+        4 => (-z, -y),
+        _ => (z, -y),
+    }
+}
+
+// Test de planos de una caja alineada: (t de entrada, cara de entrada, t de salida, cara de salida)
+pub fn slab_intersect(min: &Vec3, max: &Vec3, origin: Vec3, direction: Vec3, t_min: f64, t_max: f64) -> Option<(f64, usize, f64, usize)> {
+    let mut t_entry = f64::NEG_INFINITY;
+    let mut t_exit = f64::INFINITY;
+    let mut entry_face = 0;
+    let mut exit_face = 0;
+
+    // (origen, dirección, mínimo, máximo, cara al entrar por el lado negativo, por el positivo)
+    let axes = [
+        (origin.x, direction.x, min.x, max.x, 5, 4),
+        (origin.y, direction.y, min.y, max.y, 1, 0),
+        (origin.z, direction.z, min.z, max.z, 2, 3),
+    ];
+    for (o, d, lo, hi, negative_face, positive_face) in axes {
+        if d.abs() < 1e-12 {
+            // Rayo paralelo a los planos: o está entre ellos o no hay impacto
+            if o < lo || o > hi {
+                return None;
+            }
+            continue;
+        }
+        let inv_d = 1.0 / d;
+        let (mut t0, mut t1) = ((lo - o) * inv_d, (hi - o) * inv_d);
+        let (mut face0, mut face1) = (negative_face, positive_face);
+        if inv_d < 0.0 {
+            std::mem::swap(&mut t0, &mut t1);
+            std::mem::swap(&mut face0, &mut face1);
+        }
+        if t0 > t_entry {
+            t_entry = t0;
+            entry_face = face0;
+        }
+        if t1 < t_exit {
+            t_exit = t1;
+            exit_face = face1;
+        }
+    }
+
+    if t_entry > t_exit || t_exit < t_min || t_entry > t_max {
+        return None;
+    }
+    Some((t_entry, entry_face, t_exit, exit_face))
+}
@@ -0,0 +1,461 @@
+use crate::aov::{Aov, AovSample};
+use crate::camera::Camera;
+use crate::denoise::BandDenoiser;
+use crate::raytracer::{self, Band, RenderMonitor, RenderSettings};
+use crate::scene::Scene;
+use crate::tonemap::ToneMapping;
+use image::{ImageFormat, Rgb, RgbImage};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+// Qué se guarda de un render y dónde
+#[derive(Clone, Copy)]
+pub struct Output<'a> {
+    pub path: &'a str,
+    // Pases auxiliares que se guardan con el render
+    pub aovs: &'a [Aov],
+    pub tone_mapping: &'a ToneMapping,
+}
+
+// Filas de cada bloque de un EXR con compresión ZIP16
+const EXR_BLOCK_ROWS: u32 = 16;
+
+// Renderiza y guarda a la vez: cada fila de teselas va al archivo en cuanto se
+// termina, así que la memoria no depende del alto de la imagen (salvo en los
+// formatos de MemoryWriter). .exr y .hdr guardan el color lineal sin tocar;
+// el resto de formatos, el resultado de la exposición y la curva de tonos en
+// sRGB. Los pases auxiliares van como capas del mismo .exr, o si no en PNG
+// aparte (render_depth.png...).
+//
+// Todo se escribe en <ruta>.part y se renombra al acabar: un render cancelado
+// no se guarda, para no pisar un render bueno anterior en la misma ruta (lo
+// hecho queda en el checkpoint si lo hay). Devuelve si se guardó
+pub fn render_to_file(
+    scene: &Scene,
+    camera: &Camera,
+    width: u32,
+    height: u32,
+    settings: &RenderSettings,
+    monitor: &RenderMonitor,
+    output: &Output,
+) -> bool {
+    let Output { path, aovs, tone_mapping } = *output;
+    let region = settings.region_within(width, height);
+    let (region_width, region_height) = (region.width, region.height);
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let exr = extension.as_deref() == Some("exr");
+    let aov_paths: Vec<(Aov, String)> = aovs
+        .iter()
+        .filter(|_| !exr)
+        .map(|&aov| (aov, with_extension(&suffixed_path(path, aov.name()), "png")))
+        .collect();
+    let parts: Vec<String> = std::iter::once(path)
+        .chain(aov_paths.iter().map(|(_, path)| path.as_str()))
+        .map(|path| format!("{}.part", path))
+        .collect();
+
+    // Con el denoiser, las filas llegan a los archivos por tramos, cuando ya
+    // tienen debajo el contexto que necesita el filtro
+    let render = |write: &mut dyn FnMut(&Band) -> Result<(), String>| -> Result<bool, String> {
+        let with_aovs = !aovs.is_empty() || settings.denoise;
+        let mut denoiser = settings.denoise.then(|| BandDenoiser::new(region_width));
+        if settings.denoise {
+            println!("Quitando el ruido por franjas mientras se renderiza...");
+        }
+        let finished = raytracer::render_bands(scene, camera, (width, height), settings, with_aovs, monitor, &mut |band| {
+            match &mut denoiser {
+                Some(denoiser) => denoiser.push(band).map_or(Ok(()), |band| write(&band)),
+                None => write(&band),
+            }
+        })?;
+        if let (true, Some(denoiser)) = (finished, denoiser) {
+            if let Some(band) = denoiser.finish() {
+                write(&band)?;
+            }
+        }
+        Ok(finished)
+    };
+
+    let result = if exr {
+        write_exr(&parts[0], region_width, region_height, aovs, render)
+    } else {
+        let open = || -> Result<Vec<Box<dyn BandWriter>>, String> {
+            let (width, height) = (region_width, region_height);
+            let mut writers: Vec<Box<dyn BandWriter>> = vec![match extension.as_deref() {
+                Some("hdr") => Box::new(RadianceWriter::create(&parts[0], width, height)?),
+                Some("png") => Box::new(PngWriter::create(&parts[0], width, height, *tone_mapping)?),
+                _ => Box::new(MemoryWriter::new(path, &parts[0], width, height, *tone_mapping)?),
+            }];
+            for ((aov, _), part) in aov_paths.iter().zip(&parts[1..]) {
+                writers.push(Box::new(AovPngWriter::create(*aov, part, width, height)?));
+            }
+            Ok(writers)
+        };
+        open().and_then(|mut writers| {
+            let finished = render(&mut |band| writers.iter_mut().try_for_each(|writer| writer.write(band)))?;
+            if finished {
+                writers.into_iter().try_for_each(|writer| writer.finish())?;
+            }
+            Ok(finished)
+        })
+    };
+
+    let finished = match result {
+        Ok(finished) => finished,
+        Err(err) => {
+            remove_parts(&parts);
+            eprintln!("Error al guardar {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    if !finished {
+        remove_parts(&parts);
+        println!("Render cancelado: no se guarda {}", path);
+        return false;
+    }
+
+    let finals = std::iter::once(path).chain(aov_paths.iter().map(|(_, path)| path.as_str()));
+    for (part, path) in parts.iter().zip(finals) {
+        if let Err(err) = std::fs::rename(part, path) {
+            eprintln!("Error al guardar {}: {}", path, err);
+            std::process::exit(1);
+        }
+        println!("Imagen guardada en {}", path);
+    }
+    if aovs.contains(&Aov::Material) {
+        let legend_path = with_extension(&suffixed_path(path, "material"), "txt");
+        if let Err(err) = std::fs::write(&legend_path, legend(&scene.material_names())) {
+            eprintln!("Error al guardar {}: {}", legend_path, err);
+            std::process::exit(1);
+        }
+        println!("Identificadores de bloque en {}", legend_path);
+    }
+    true
+}
+
+// Borra lo escrito de un render que no se termina, con la profundidad en crudo
+// de AovPngWriter si la hay
+fn remove_parts(parts: &[String]) {
+    for part in parts {
+        std::fs::remove_file(part).ok();
+        std::fs::remove_file(format!("{}.raw", part)).ok();
+    }
+}
+
+// Una línea "identificador nombre" por bloque; el identificador de cada uno es
+// su posición más uno
+fn legend(materials: &[String]) -> String {
+    materials
+        .iter()
+        .enumerate()
+        .map(|(index, name)| format!("{} {}\n", index + 1, name))
+        .collect()
+}
+
+fn create(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path).map(BufWriter::new).map_err(|err| err.to_string())
+}
+
+// Destino de las filas del render, de arriba abajo
+trait BandWriter {
+    fn write(&mut self, band: &Band) -> Result<(), String>;
+    // Se llama solo si el render se termina
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+fn png_stream(path: &str, width: u32, height: u32, (color, depth): (png::ColorType, png::BitDepth)) -> Result<png::StreamWriter<'static, BufWriter<File>>, String> {
+    let mut encoder = png::Encoder::new(create(path)?, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder
+        .write_header()
+        .and_then(|writer| writer.into_stream_writer())
+        .map_err(|err| err.to_string())
+}
+
+// PNG de 8 bits con la exposición y la curva de tonos aplicadas
+struct PngWriter {
+    stream: png::StreamWriter<'static, BufWriter<File>>,
+    tone_mapping: ToneMapping,
+}
+
+impl PngWriter {
+    fn create(path: &str, width: u32, height: u32, tone_mapping: ToneMapping) -> Result<Self, String> {
+        let stream = png_stream(path, width, height, (png::ColorType::Rgb, png::BitDepth::Eight))?;
+        Ok(PngWriter { stream, tone_mapping })
+    }
+}
+
+impl BandWriter for PngWriter {
+    fn write(&mut self, band: &Band) -> Result<(), String> {
+        let bytes: Vec<u8> = band.color.chunks(3).flat_map(|c| self.tone_mapping.map_pixel([c[0] as f64, c[1] as f64, c[2] as f64])).collect();
+        self.stream.write_all(&bytes).map_err(|err| err.to_string())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        self.stream.finish().map_err(|err| err.to_string())
+    }
+}
+
+// Radiance .hdr: image no lo escribe por partes, así que las filas se
+// codifican aquí, con el mismo RLE por canales que su HdrEncoder pero sin
+// buscar repeticiones
+struct RadianceWriter {
+    file: BufWriter<File>,
+    width: u32,
+}
+
+impl RadianceWriter {
+    fn create(path: &str, width: u32, height: u32) -> Result<Self, String> {
+        let mut file = create(path)?;
+        write!(file, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)
+            .map_err(|err| err.to_string())?;
+        Ok(RadianceWriter { file, width })
+    }
+}
+
+impl BandWriter for RadianceWriter {
+    fn write(&mut self, band: &Band) -> Result<(), String> {
+        use image::codecs::hdr::to_rgbe8;
+
+        let width = self.width as usize;
+        let mut bytes = Vec::with_capacity(band.color.len() / 3 * 4 + band.height as usize * 8);
+        for row in band.color.chunks(width * 3) {
+            let pixels: Vec<_> = row.chunks(3).map(|c| to_rgbe8(Rgb([c[0], c[1], c[2]]))).collect();
+            // Fuera de este rango de anchos el formato no admite RLE
+            if !(8..=32_767).contains(&width) {
+                bytes.extend(pixels.iter().flat_map(|p| [p.c[0], p.c[1], p.c[2], p.e]));
+                continue;
+            }
+            bytes.extend_from_slice(&[2, 2, (width >> 8) as u8, width as u8]);
+            for channel in 0..4 {
+                let values: Vec<u8> = pixels.iter().map(|p| if channel < 3 { p.c[channel] } else { p.e }).collect();
+                for literal in values.chunks(128) {
+                    bytes.push(literal.len() as u8);
+                    bytes.extend_from_slice(literal);
+                }
+            }
+        }
+        self.file.write_all(&bytes).map_err(|err| err.to_string())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        self.file.flush().map_err(|err| err.to_string())
+    }
+}
+
+// Formatos que image solo sabe codificar con la imagen entera (.jpg, .bmp...):
+// se guarda ya en 8 bits, 3 bytes por píxel
+struct MemoryWriter {
+    format: ImageFormat,
+    part: String,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    tone_mapping: ToneMapping,
+}
+
+impl MemoryWriter {
+    fn new(path: &str, part: &str, width: u32, height: u32, tone_mapping: ToneMapping) -> Result<Self, String> {
+        let format = ImageFormat::from_path(path).map_err(|err| err.to_string())?;
+        Ok(MemoryWriter {
+            format,
+            part: part.to_string(),
+            width,
+            height,
+            pixels: Vec::new(),
+            tone_mapping,
+        })
+    }
+}
+
+impl BandWriter for MemoryWriter {
+    fn write(&mut self, band: &Band) -> Result<(), String> {
+        self.pixels.extend(band.color.chunks(3).flat_map(|c| self.tone_mapping.map_pixel([c[0] as f64, c[1] as f64, c[2] as f64])));
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        let img = RgbImage::from_raw(self.width, self.height, self.pixels).ok_or("faltan filas del render")?;
+        img.save_with_format(&self.part, self.format).map_err(|err| err.to_string())
+    }
+}
+
+// PNG de un pase auxiliar. La profundidad se normaliza con el punto más lejano
+// de toda la imagen, que no se conoce hasta el final: mientras tanto va en
+// crudo a <png>.raw
+struct AovPngWriter {
+    aov: Aov,
+    path: String,
+    width: u32,
+    height: u32,
+    output: AovOutput,
+}
+
+enum AovOutput {
+    Png(Box<png::StreamWriter<'static, BufWriter<File>>>),
+    Depth { raw: BufWriter<File>, max: f32 },
+}
+
+impl AovPngWriter {
+    fn create(aov: Aov, path: &str, width: u32, height: u32) -> Result<Self, String> {
+        let output = match aov {
+            Aov::Depth => AovOutput::Depth { raw: create(&format!("{}.raw", path))?, max: 0.0 },
+            _ => AovOutput::Png(Box::new(png_stream(path, width, height, aov.png_format())?)),
+        };
+        Ok(AovPngWriter { aov, path: path.to_string(), width, height, output })
+    }
+}
+
+impl BandWriter for AovPngWriter {
+    fn write(&mut self, band: &Band) -> Result<(), String> {
+        let result = match &mut self.output {
+            AovOutput::Png(stream) => {
+                let mut bytes = Vec::new();
+                for sample in &band.aovs {
+                    self.aov.png_pixel(sample, 0.0, &mut bytes);
+                }
+                stream.write_all(&bytes)
+            }
+            AovOutput::Depth { raw, max } => {
+                let bytes: Vec<u8> = band.aovs.iter().flat_map(|s| s.depth.to_le_bytes()).collect();
+                *max = band.aovs.iter().map(|s| s.depth).filter(|d| d.is_finite()).fold(*max, f32::max);
+                raw.write_all(&bytes)
+            }
+        };
+        result.map_err(|err| err.to_string())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        let (raw, max) = match self.output {
+            AovOutput::Png(stream) => return stream.finish().map_err(|err| err.to_string()),
+            AovOutput::Depth { raw, max } => (raw, max.max(f32::EPSILON)),
+        };
+        raw.into_inner().map_err(|err| err.to_string())?;
+        println!("Profundidad máxima: {:.2}", max);
+        let raw_path = format!("{}.raw", self.path);
+        let mut stream = png_stream(&self.path, self.width, self.height, self.aov.png_format())?;
+        let mut raw = BufReader::new(File::open(&raw_path).map_err(|err| err.to_string())?);
+        let mut row = vec![0; self.width as usize * 4];
+        for _ in 0..self.height {
+            raw.read_exact(&mut row).map_err(|err| err.to_string())?;
+            let mut bytes = Vec::with_capacity(self.width as usize * 2);
+            for depth in row.chunks(4) {
+                let depth = f32::from_le_bytes(depth.try_into().unwrap());
+                self.aov.png_pixel(&AovSample { depth, ..AovSample::default() }, max, &mut bytes);
+            }
+            stream.write_all(&bytes).map_err(|err| err.to_string())?;
+        }
+        stream.finish().map_err(|err| err.to_string())?;
+        std::fs::remove_file(&raw_path).ok();
+        Ok(())
+    }
+}
+
+// EXR por bloques de EXR_BLOCK_ROWS filas: la capa beauty con el color lineal
+// y una capa por pase. exr escribe el archivo dentro de un cierre, así que el
+// render se lanza desde él
+fn write_exr(
+    path: &str,
+    width: u32,
+    height: u32,
+    aovs: &[Aov],
+    render: impl FnOnce(&mut dyn FnMut(&Band) -> Result<(), String>) -> Result<bool, String>,
+) -> Result<bool, String> {
+    use exr::block::writer::ChunksWriter;
+    use exr::block::{BlockIndex, UncompressedBlock};
+    use exr::compression::Compression;
+    use exr::math::Vec2;
+    use exr::meta::attribute::{ChannelDescription, LineOrder, SampleType, Text};
+    use exr::meta::header::Header;
+    use exr::meta::BlockDescription;
+
+    let layer = |name: &str, channels: &[&str], sample_type: SampleType| {
+        let channels = channels.iter().map(|&channel| ChannelDescription::named(channel, sample_type)).collect();
+        Header::new(Text::from(name), (width as usize, height as usize), channels)
+            .with_encoding(Compression::ZIP16, BlockDescription::ScanLines, LineOrder::Increasing)
+    };
+    let mut headers: exr::meta::Headers = std::iter::once(layer("beauty", &["B", "G", "R"], SampleType::F32))
+        .chain(aovs.iter().map(|aov| {
+            let (name, channels) = aov.exr_layer();
+            layer(name, channels, if aov.is_id() { SampleType::U32 } else { SampleType::F32 })
+        }))
+        .collect();
+    // Sin pases es un EXR normal de una capa, sin nombre
+    if aovs.is_empty() {
+        headers[0].own_attributes.layer_name = None;
+    }
+
+    // Resultado del render, que exr no sabe devolver
+    let mut outcome = Ok(false);
+    let file = create(path)?;
+    let written = exr::block::write(file, headers, true, |meta, chunk_writer| {
+        let width = width as usize;
+        // Filas recibidas que aún no completan un bloque, desde la fila first
+        let (mut first, mut color, mut samples) = (0, Vec::new(), Vec::new());
+        let mut write_block = |first: u32, rows: u32, color: &[f32], samples: &[AovSample]| -> Result<(), String> {
+            for (layer, header) in meta.headers.iter().enumerate() {
+                let index = BlockIndex {
+                    layer,
+                    pixel_position: Vec2(0, first as usize),
+                    pixel_size: Vec2(width, rows as usize),
+                    level: Vec2(0, 0),
+                };
+                let block = UncompressedBlock::from_lines(&header.channels, index, |line| {
+                    let start = (line.location.position.1 - first as usize) * width;
+                    let channel = line.location.channel;
+                    let written = match layer.checked_sub(1).map(|aov| aovs[aov]) {
+                        None => line.write_samples(|x| color[(start + x) * 3 + 2 - channel]),
+                        Some(aov) if aov.is_id() => line.write_samples(|x| aov.exr_id(&samples[start + x])),
+                        Some(aov) => line.write_samples(|x| aov.exr_value(&samples[start + x], channel)),
+                    };
+                    written.expect("el tamaño de la línea no coincide con su canal");
+                });
+                let chunk = block.compress_to_chunk(&meta.headers).map_err(|err| err.to_string())?;
+                chunk_writer
+                    .write_chunk((first / EXR_BLOCK_ROWS) as usize, chunk)
+                    .map_err(|err| err.to_string())?;
+            }
+            Ok(())
+        };
+
+        outcome = render(&mut |band| {
+            color.extend_from_slice(&band.color);
+            samples.extend_from_slice(&band.aovs);
+            let received = band.y + band.height;
+            while first + EXR_BLOCK_ROWS <= received || (received == height && first < received) {
+                let rows = EXR_BLOCK_ROWS.min(received - first);
+                let pixels = width * rows as usize;
+                write_block(first, rows, &color[..pixels * 3], &samples[..pixels.min(samples.len())])?;
+                color.drain(..pixels * 3);
+                samples.drain(..pixels.min(samples.len()));
+                first += rows;
+            }
+            Ok(())
+        });
+        match outcome {
+            Ok(true) => Ok(()),
+            _ => Err(exr::error::Error::Aborted),
+        }
+    });
+    match written {
+        Err(exr::error::Error::Aborted) => outcome,
+        Err(err) => Err(err.to_string()),
+        Ok(()) => Ok(true),
+    }
+}
+
+fn with_extension(path: &str, extension: &str) -> String {
+    std::path::Path::new(path).with_extension(extension).to_string_lossy().into_owned()
+}
+
+// render.png -> render_px.png, render_0001.png...
+pub fn suffixed_path(output: &str, suffix: &str) -> String {
+    let path = std::path::Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("render");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    path.with_file_name(format!("{}_{}.{}", stem, suffix, extension)).to_string_lossy().into_owned()
+}
@@ -0,0 +1,200 @@
+use crate::vector::Vec3;
+use std::f64::consts::PI;
+
+// Modelo analítico de cielo de Preetham, Shirley y Smits (1999).
+// Se precalcula una vez por posición del sol y turbidez.
+
+// Escala de luminancia (kcd/m²) a color lineal del renderer
+const LUMINANCE_SCALE: f64 = 0.06;
+
+// Radiancia relativa del disco solar frente al cielo
+const SUN_DISC_RADIANCE: f64 = 30.0;
+const SUN_DISC_COS: f64 = 0.99995;
+
+// Longitudes de onda (micrómetros) usadas para el rojo, verde y azul
+const WAVELENGTHS: [f64; 3] = [0.65, 0.57, 0.475];
+
+#[derive(Clone, Copy)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    fn eval(&self, cos_theta: f64, gamma: f64) -> f64 {
+        (1.0 + self.a * (self.b / cos_theta).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos() * gamma.cos())
+    }
+}
+
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    perez_y: Perez,
+    perez_x: Perez,
+    perez_yc: Perez,
+    // Valores en el cenit divididos por F(0, theta_s)
+    zenith_y: f64,
+    zenith_x: f64,
+    zenith_yc: f64,
+    sun_color: [f64; 3],
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3, turbidity: f64) -> Self {
+        let t = turbidity.clamp(1.7, 10.0);
+        let sun_direction = sun_direction.normalize();
+        // El modelo solo es válido con el sol sobre el horizonte
+        let theta_s = sun_direction.y.clamp(0.001, 1.0).acos();
+
+        let perez_y = Perez {
+            a: 0.1787 * t - 1.4630,
+            b: -0.3554 * t + 0.4275,
+            c: -0.0227 * t + 5.3251,
+            d: 0.1206 * t - 2.5771,
+            e: -0.0670 * t + 0.3703,
+        };
+        let perez_x = Perez {
+            a: -0.0193 * t - 0.2592,
+            b: -0.0665 * t + 0.0008,
+            c: -0.0004 * t + 0.2125,
+            d: -0.0641 * t - 0.8989,
+            e: -0.0033 * t + 0.0452,
+        };
+        let perez_yc = Perez {
+            a: -0.0167 * t - 0.2608,
+            b: -0.0950 * t + 0.0092,
+            c: -0.0079 * t + 0.2102,
+            d: -0.0441 * t - 1.6537,
+            e: -0.0109 * t + 0.0529,
+        };
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+        let t2 = t * t;
+        let th = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let zenith_x = chromaticity(
+            [t2, t, 1.0],
+            [
+                [0.00166, -0.00375, 0.00209, 0.0],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ],
+            th,
+        );
+        let zenith_yc = chromaticity(
+            [t2, t, 1.0],
+            [
+                [0.00275, -0.00610, 0.00317, 0.0],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ],
+            th,
+        );
+
+        PreethamSky {
+            sun_direction,
+            perez_y,
+            perez_x,
+            perez_yc,
+            zenith_y: zenith_luminance / perez_y.eval(1.0, theta_s),
+            zenith_x: zenith_x / perez_x.eval(1.0, theta_s),
+            zenith_yc: zenith_yc / perez_yc.eval(1.0, theta_s),
+            sun_color: sun_transmittance(theta_s, t),
+        }
+    }
+
+    // Color de la luz directa del sol tras atravesar la atmósfera
+    pub fn sun_color(&self) -> [f64; 3] {
+        self.sun_color
+    }
+
+    // Luz ambiente aproximada como el promedio del cielo en unas pocas direcciones
+    pub fn ambient(&self) -> [f64; 3] {
+        let directions = [
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.5, 0.0),
+            Vec3::new(-1.0, 0.5, 0.0),
+            Vec3::new(0.0, 0.5, 1.0),
+            Vec3::new(0.0, 0.5, -1.0),
+        ];
+        let mut sum = [0.0; 3];
+        for dir in &directions {
+            let c = self.radiance(dir);
+            sum[0] += c[0];
+            sum[1] += c[1];
+            sum[2] += c[2];
+        }
+        let scale = 0.8 / directions.len() as f64;
+        [sum[0] * scale, sum[1] * scale, sum[2] * scale]
+    }
+
+    pub fn sun_disc(&self, direction: &Vec3) -> [f64; 3] {
+        let dir = direction.normalize();
+        if dir.dot(&self.sun_direction) > SUN_DISC_COS && dir.y > 0.0 {
+            [
+                self.sun_color[0] * SUN_DISC_RADIANCE,
+                self.sun_color[1] * SUN_DISC_RADIANCE,
+                self.sun_color[2] * SUN_DISC_RADIANCE,
+            ]
+        } else {
+            [0.0; 3]
+        }
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> [f64; 3] {
+        let dir = direction.normalize();
+        // Bajo el horizonte se repite el color del horizonte
+        let cos_theta = dir.y.max(0.01);
+        let horizon_dir = Vec3::new(dir.x, cos_theta, dir.z).normalize();
+        let gamma = horizon_dir.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let luminance = self.zenith_y * self.perez_y.eval(cos_theta, gamma);
+        let x = self.zenith_x * self.perez_x.eval(cos_theta, gamma);
+        let y = self.zenith_yc * self.perez_yc.eval(cos_theta, gamma);
+
+        xyy_to_rgb(x, y, luminance * LUMINANCE_SCALE)
+    }
+}
+
+fn chromaticity(t: [f64; 3], m: [[f64; 4]; 3], th: [f64; 4]) -> f64 {
+    let mut result = 0.0;
+    for (row, ti) in m.iter().zip(t.iter()) {
+        let dot: f64 = row.iter().zip(th.iter()).map(|(a, b)| a * b).sum();
+        result += ti * dot;
+    }
+    result
+}
+
+// Atenuación del sol por dispersión de Rayleigh y aerosoles (Ångström)
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> [f64; 3] {
+    let theta_deg = theta_s.to_degrees();
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_deg).max(0.001).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let alpha = 1.3;
+
+    let mut color = [0.0; 3];
+    for (c, lambda) in color.iter_mut().zip(WAVELENGTHS.iter()) {
+        let tau_rayleigh = 0.008735 * lambda.powf(-4.08);
+        let tau_aerosol = beta * lambda.powf(-alpha);
+        *c = (-air_mass * (tau_rayleigh + tau_aerosol)).exp();
+    }
+    color
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> [f64; 3] {
+    if y <= 0.0 {
+        return [0.0; 3];
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+
+    [
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    ]
+}
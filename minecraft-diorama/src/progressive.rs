@@ -0,0 +1,83 @@
+use crate::aov::{AovBuffers, AovSample};
+use crate::camera::Camera;
+use crate::raytracer;
+use crate::rng::Rng;
+use crate::scene::Scene;
+use image::{Rgb, Rgb32FImage};
+use rayon::prelude::*;
+
+// Acumulación progresiva del visor: mientras la vista no cambia, cada fotograma
+// suma una muestra más por píxel con un desplazamiento aleatorio distinto
+pub struct Accumulation {
+    width: u32,
+    height: u32,
+    sums: Vec<[f64; 3]>,
+    pub samples: u32,
+    // Guías del denoiser; no dependen de las muestras, así que se calculan una
+    // vez tras cada reinicio
+    guides: Option<AovBuffers>,
+}
+
+impl Accumulation {
+    pub fn new(width: u32, height: u32) -> Self {
+        Accumulation {
+            width,
+            height,
+            sums: vec![[0.0; 3]; (width * height) as usize],
+            samples: 0,
+            guides: None,
+        }
+    }
+
+    // Descarta lo acumulado, al mover la cámara o cambiar la escena
+    pub fn reset(&mut self) {
+        self.sums.fill([0.0; 3]);
+        self.samples = 0;
+        self.guides = None;
+    }
+
+    pub fn add_sample(&mut self, scene: &Scene, camera: &Camera) {
+        let (width, height) = (self.width, self.height);
+        let (cone_width, spread) = camera.pixel_cone(height);
+        let sample = self.samples;
+        self.sums.par_iter_mut().enumerate().for_each(|(index, sum)| {
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            let mut rng = Rng::for_pixel(x, y, sample);
+            let u = (x as f64 + rng.next_f64()) / (width - 1) as f64;
+            let v = ((height - 1 - y) as f64 + rng.next_f64()) / (height - 1) as f64;
+            let ray = camera.sample_ray(u, v, &mut rng).with_cone(cone_width, spread);
+            let color = raytracer::trace_ray(&ray, scene, 0, &mut rng);
+            for c in 0..3 {
+                sum[c] += color[c];
+            }
+        });
+        self.samples += 1;
+
+        if self.guides.is_none() {
+            let materials = scene.material_names();
+            let guides = (0..width * height)
+                .into_par_iter()
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let u = (x as f64 + 0.5) / (width - 1) as f64;
+                    let v = ((height - 1 - y) as f64 + 0.5) / (height - 1) as f64;
+                    AovSample::trace(&camera.get_ray(u, v), camera, scene, &materials)
+                })
+                .collect();
+            self.guides = Some(AovBuffers::new(width, height, guides));
+        }
+    }
+
+    // Media de las muestras en color lineal, sin ruido si denoise
+    pub fn image(&self, denoise: bool) -> Rgb32FImage {
+        let scale = 1.0 / self.samples.max(1) as f64;
+        let img = Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            let sum = self.sums[(y * self.width + x) as usize];
+            Rgb(sum.map(|c| (c * scale) as f32))
+        });
+        match (&self.guides, denoise) {
+            (Some(guides), true) => crate::denoise::denoise(&img, guides),
+            _ => img,
+        }
+    }
+}
@@ -4,6 +4,10 @@ use crate::vector::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    // Cono del rayo para elegir el nivel de mipmap: ancho en el origen
+    // y crecimiento por unidad de distancia
+    pub cone_width: f64,
+    pub cone_spread: f64,
 }
 
 impl Ray {
@@ -11,9 +15,22 @@ impl Ray {
         Ray {
             origin,
             direction: direction.normalize(),
+            cone_width: 0.0,
+            cone_spread: 0.0,
         }
     }
     
+    pub fn with_cone(mut self, width: f64, spread: f64) -> Self {
+        self.cone_width = width;
+        self.cone_spread = spread;
+        self
+    }
+    
+    // Ancho del cono a la distancia t
+    pub fn footprint(&self, t: f64) -> f64 {
+        self.cone_width + self.cone_spread * t
+    }
+    
     pub fn at(&self, t: f64) -> Vec3 {
         self.origin + self.direction * t
     }
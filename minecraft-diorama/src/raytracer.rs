@@ -1,183 +1,636 @@
+use crate::aov::AovSample;
 use crate::scene::Scene;
+use crate::bsdf::Surface;
+use crate::checkpoint::{Checkpoint, PixelState};
 use crate::camera::Camera;
+use crate::cube::HitRecord;
 use crate::ray::Ray;
+use crate::rng::Rng;
 use crate::vector::Vec3;
-use crate::cube::HitRecord;
-use image::{RgbImage, Rgb};
 use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const MAX_DEPTH: u32 = 2;  
+const MAX_PATH_DEPTH: u32 = 5;
+// Por debajo de esta rugosidad el reflejo se trata como un espejo (un solo rayo)
+const MIRROR_ROUGHNESS: f64 = 0.05;
+
+// Algoritmo de transporte de luz usado por el render offline
+#[derive(Clone, Copy, PartialEq)]
+pub enum Integrator {
+    // Trazado clásico: luz directa + ambiente, reflexión y refracción
+    Whitted,
+    // Path tracing con muestreo por importancia del cielo/mapa de entorno
+    PathTraced,
+}
+
+// Lado en píxeles de las teselas en que se reparte el render offline
+pub const TILE_SIZE: u32 = 32;
+
+// Calidad del render offline
+#[derive(Clone, Copy)]
+pub struct RenderSettings {
+    // Muestras por píxel
+    pub samples: u32,
+    pub integrator: Integrator,
+    // Quitar el ruido con el albedo, las normales y la profundidad como guía
+    pub denoise: bool,
+    // Solo se renderiza esta parte de la imagen; None es la imagen entera
+    pub region: Option<Region>,
+    // Semilla de los generadores de cada píxel
+    pub seed: u32,
+}
+
+impl RenderSettings {
+    // Lo que se renderiza de una imagen de width x height
+    pub fn region_within(&self, width: u32, height: u32) -> Region {
+        self.region.map_or(Region::new(0, 0, width, height), |region| region.within(width, height))
+    }
+}
+
+// Rectángulo en píxeles de la imagen completa, con y hacia abajo
+#[derive(Clone, Copy)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Region { x, y, width, height }
+    }
 
-pub fn render(scene: &Scene, camera: &Camera, width: u32, height: u32, samples: u32) -> RgbImage {
-    let mut img = RgbImage::new(width, height);
-    let total_pixels = width * height;
+    // La parte de la región que cae dentro de una imagen de width x height
+    fn within(self, width: u32, height: u32) -> Region {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Region::new(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+
+    // Teselas de TILE_SIZE en orden de lectura; las del borde pueden ser menores
+    fn tiles(self) -> Vec<Region> {
+        let mut tiles = Vec::new();
+        for y in (0..self.height).step_by(TILE_SIZE as usize) {
+            for x in (0..self.width).step_by(TILE_SIZE as usize) {
+                tiles.push(Region::new(x, y, TILE_SIZE.min(self.width - x), TILE_SIZE.min(self.height - y)));
+            }
+        }
+        tiles
+    }
+}
+
+// Avance de un render, tras cada tesela terminada
+#[derive(Clone, Copy)]
+pub struct Progress {
+    pub tiles_done: usize,
+    pub tiles: usize,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        self.tiles_done as f64 / self.tiles.max(1) as f64
+    }
+
+    // Tiempo restante suponiendo que las teselas que faltan tardan lo mismo
+    pub fn eta(&self) -> Option<Duration> {
+        if self.tiles_done == 0 {
+            return None;
+        }
+        let remaining = (self.tiles - self.tiles_done) as f64 / self.tiles_done as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+// Quien lanza el render recibe el progreso y puede pararlo: al poner cancel a
+// true no se empiezan más teselas y la fila a medias no se entrega. Con un
+// checkpoint, cada píxel sigue desde las muestras guardadas en él
+pub struct RenderMonitor<'a> {
+    pub on_progress: &'a (dyn Fn(Progress) + Sync),
+    pub cancel: &'a AtomicBool,
+    pub checkpoint: Option<&'a Checkpoint>,
+}
+
+// Filas terminadas de la región, con el color lineal sin recortar (el paso a
+// 8 bits lo hace tonemap)
+pub struct Band {
+    // Primera fila dentro de la región
+    pub y: u32,
+    pub height: u32,
+    // RGB de cada píxel, fila a fila
+    pub color: Vec<f32>,
+    // Muestras de los pases auxiliares; vacío si no se piden
+    pub aovs: Vec<AovSample>,
+}
+
+// Renderiza la región de arriba abajo, una fila de teselas detrás de otra, y
+// entrega cada fila a on_band en cuanto se termina (con los pases auxiliares si
+// with_aovs). En memoria solo está la fila en curso: lo que se guarda lo lleva
+// on_band a disco. Devuelve false si se cancela antes de acabar; un error de
+// on_band para el render
+pub fn render_bands(
+    scene: &Scene,
+    camera: &Camera,
+    (width, height): (u32, u32),
+    settings: &RenderSettings,
+    with_aovs: bool,
+    monitor: &RenderMonitor,
+    on_band: &mut dyn FnMut(Band) -> Result<(), String>,
+) -> Result<bool, String> {
+    let region = settings.region_within(width, height);
+    let tiles = region.tiles();
+    let columns = region.width.div_ceil(TILE_SIZE).max(1) as usize;
+    let materials = scene.material_names();
+    println!("Renderizando {} píxeles en {} teselas con paralelización Rayon...", region.width * region.height, tiles.len());
     
-    println!("Renderizando con paralelización Rayon...");
+    let tiles_done = Mutex::new(0);
+    let start = Instant::now();
+    let mut finished = true;
+    let mut result = Ok(());
     
-    // Convertir imagen a un vector de píxeles para procesamiento paralelo
-    let pixels: Vec<(u32, u32, Rgb<u8>)> = (0..height)
-        .into_par_iter()  // Paralelizar por filas
-        .flat_map(|y| {
-            (0..width).into_par_iter().map(move |x| {
-                let mut color = [0.0, 0.0, 0.0];
-                
-                // Anti-aliasing con múltiples muestras
-                for _ in 0..samples {
-                    let u = (x as f64 + rand_float()) / (width - 1) as f64;
-                    let v = ((height - 1 - y) as f64 + rand_float()) / (height - 1) as f64;
-                    
-                    let ray = camera.get_ray(u, v);
-                    let sample_color = trace_ray(&ray, scene, 0);
-                    
-                    color[0] += sample_color[0];
-                    color[1] += sample_color[1];
-                    color[2] += sample_color[2];
+    for row in tiles.chunks(columns) {
+        let rendered: Vec<_> = row.par_iter().map(|tile| {
+            if monitor.cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let mut states = match monitor.checkpoint {
+                Some(checkpoint) => checkpoint.tile(tile.x, tile.y, tile.width, tile.height),
+                None => (tile.y..tile.y + tile.height)
+                    .flat_map(|y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+                    .map(|(x, y)| PixelState::new(region.x + x, region.y + y, settings.seed))
+                    .collect(),
+            };
+            let mut colors = Vec::with_capacity(states.len());
+            let mut tile_aovs = Vec::new();
+            let mut pixel_states = states.iter_mut();
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    // Coordenadas en la imagen completa
+                    let (fx, fy) = (region.x + x, region.y + y);
+                    let state = pixel_states.next().unwrap();
+                    add_samples(scene, camera, (fx, fy), width, height, settings, state);
+                    let scale = 1.0 / state.samples.max(1) as f64;
+                    colors.push(state.sum.map(|c| (c * scale) as f32));
+                    if with_aovs {
+                        let (u, v) = camera.pixel_uv((fx, fy), (0.5, 0.5), width, height);
+                        tile_aovs.push(AovSample::trace(&camera.get_ray(u, v), camera, scene, &materials));
+                    }
                 }
-                
-                // Promediar las muestras
-                let scale = 1.0 / samples as f64;
-                color[0] = (color[0] * scale).sqrt(); // Gamma correction
-                color[1] = (color[1] * scale).sqrt();
-                color[2] = (color[2] * scale).sqrt();
-                
-                let pixel = Rgb([
-                    (color[0].clamp(0.0, 1.0) * 255.0) as u8,
-                    (color[1].clamp(0.0, 1.0) * 255.0) as u8,
-                    (color[2].clamp(0.0, 1.0) * 255.0) as u8,
-                ]);
-                
-                (x, y, pixel)
-            })
-        })
-        .collect();
+            }
+            
+            if let Some(checkpoint) = monitor.checkpoint {
+                checkpoint.update(tile.x, tile.y, tile.width, states);
+            }
+            
+            let mut tiles_done = tiles_done.lock().unwrap();
+            *tiles_done += 1;
+            (monitor.on_progress)(Progress { tiles_done: *tiles_done, tiles: tiles.len(), elapsed: start.elapsed() });
+            Some((colors, tile_aovs))
+        }).collect();
+        
+        // Una fila con teselas sin hacer no se entrega
+        let Some(rendered) = rendered.into_iter().collect::<Option<Vec<_>>>() else {
+            finished = false;
+            break;
+        };
+        let band_height = row[0].height;
+        let pixels = (region.width * band_height) as usize;
+        let mut band = Band {
+            y: row[0].y,
+            height: band_height,
+            color: vec![0.0; pixels * 3],
+            aovs: if with_aovs { vec![AovSample::default(); pixels] } else { Vec::new() },
+        };
+        for (tile, (colors, tile_aovs)) in row.iter().zip(rendered) {
+            for (i, color) in colors.into_iter().enumerate() {
+                let (x, y) = (tile.x + i as u32 % tile.width, i as u32 / tile.width);
+                let index = (y * region.width + x) as usize;
+                band.color[index * 3..index * 3 + 3].copy_from_slice(&color);
+                if with_aovs {
+                    band.aovs[index] = tile_aovs[i];
+                }
+            }
+        }
+        if let Err(err) = on_band(band) {
+            result = Err(err);
+            break;
+        }
+    }
     
-    // Escribir los píxeles en la imagen
-    for (x, y, pixel) in pixels {
-        img.put_pixel(x, y, pixel);
+    if let Some(checkpoint) = monitor.checkpoint {
+        checkpoint.save();
     }
+    result?;
+    let tiles_done = tiles_done.into_inner().unwrap();
+    if finished {
+        println!("Renderizado completado!");
+    } else {
+        // El salto de línea termina la línea de progreso
+        println!("\nRender cancelado con {} de {} teselas terminadas", tiles_done, tiles.len());
+    }
+    Ok(finished)
+}
+
+// Añade muestras al píxel (x, y) de una imagen de width x height hasta llegar a
+// settings.samples. Cada muestra sigue la secuencia del generador del píxel, así
+// que da igual tomarlas de una vez o en varios renders
+fn add_samples(scene: &Scene, camera: &Camera, (x, y): (u32, u32), width: u32, height: u32, settings: &RenderSettings, pixel: &mut PixelState) {
+    let (cone_width, spread) = camera.pixel_cone(height);
+    let rng = &mut pixel.rng;
     
-    println!("Renderizado completado!");
-    img
+    // Anti-aliasing con múltiples muestras
+    while pixel.samples < settings.samples {
+        let jitter = (rng.next_f64(), rng.next_f64());
+        let (u, v) = camera.pixel_uv((x, y), jitter, width, height);
+        
+        let ray = camera.sample_ray(u, v, rng).with_cone(cone_width, spread);
+        let mut sample_color = match settings.integrator {
+            Integrator::Whitted => trace_ray(&ray, scene, 0, rng),
+            Integrator::PathTraced => trace_path(&ray, scene, rng),
+        };
+        
+        // Rayos de luz volumétricos sobre el rayo primario
+        if let Some(shafts) = &scene.atmosphere.light_shafts {
+            let t_max = scene.intersect(&ray, 0.001, f64::INFINITY).map_or(f64::INFINITY, |hit| hit.t);
+            let (transmittance, inscatter) = shafts.march(&ray, t_max, scene, rng);
+            for c in 0..3 {
+                sample_color[c] = sample_color[c] * transmittance[c] + inscatter[c];
+            }
+        }
+        
+        for (sum, sample) in pixel.sum.iter_mut().zip(sample_color) {
+            *sum += sample;
+        }
+        pixel.samples += 1;
+    }
+}
+
+// rng solo se usa para repartir los rayos de los reflejos rugosos
+pub fn trace_ray(ray: &Ray, scene: &Scene, depth: u32, rng: &mut Rng) -> [f64; 3] {
+    trace(ray, scene, depth, rng).0
 }
 
-pub fn trace_ray(ray: &Ray, scene: &Scene, depth: u32) -> [f64; 3] {
+// Igual que trace_ray, pero devuelve también la distancia recorrida por el rayo
+fn trace(ray: &Ray, scene: &Scene, depth: u32, rng: &mut Rng) -> ([f64; 3], f64) {
     if depth >= MAX_DEPTH {
-        return [0.0, 0.0, 0.0];
+        return ([0.0, 0.0, 0.0], f64::INFINITY);
     }
     
     // Buscar la intersección más cercana
-    let mut closest_hit: Option<HitRecord> = None;
-    let mut closest_t = f64::INFINITY;
+    let hit = scene.intersect(ray, 0.001, f64::INFINITY);
+    let underwater = scene.is_underwater(&ray.origin);
+    let distance = hit.as_ref().map_or(f64::INFINITY, |hit| hit.t);
+    let (transmittance, inscatter) = scene.atmosphere.segment(&scene.sky, &ray.direction, distance, underwater);
     
-    for cube in &scene.cubes {
-        if let Some(hit) = cube.intersect(ray, 0.001, closest_t) {
-            closest_t = hit.t;
-            closest_hit = Some(hit);
-        }
-    }
-    
-    if let Some(hit) = closest_hit {
-        // Obtener el color de la textura
-        let texture_color = hit.material.get_color(hit.face, hit.u, hit.v);
-        let base_color = [
-            texture_color[0] as f64 / 255.0,
-            texture_color[1] as f64 / 255.0,
-            texture_color[2] as f64 / 255.0,
-        ];
+    let color = if let Some(hit) = hit {
+        let front_face = hit.normal.dot(&ray.direction) < 0.0;
+        let normal = if front_face { hit.normal } else { -hit.normal };
+        let surface = surface_at(ray, &hit, &normal, scene);
+        let view = -ray.direction;
         
-        // Iluminación simple (luz direccional + ambiente)
-        let light_dir = Vec3::new(0.5, 1.0, 0.3).normalize();
-        let light_intensity = hit.normal.dot(&light_dir).max(0.0);
-        let ambient = 0.4;
-        let diffuse = 0.6 * light_intensity;
-        let lighting = (ambient + diffuse).min(1.0);
-        
-        let mut final_color = [
-            base_color[0] * lighting,
-            base_color[1] * lighting,
-            base_color[2] * lighting,
+        // Iluminación simple (luz del sol o de la luna + ambiente del cielo)
+        let (light_dir, light_color) = scene.sky.light();
+        let light_intensity = surface.normal.dot(&light_dir).max(0.0);
+        let ambient = scene.sky.ambient();
+        let lighting = [
+            (ambient[0] + 0.6 * light_intensity * light_color[0]).min(1.0),
+            (ambient[1] + 0.6 * light_intensity * light_color[1]).min(1.0),
+            (ambient[2] + 0.6 * light_intensity * light_color[2]).min(1.0),
         ];
         
+        // Difuso más el brillo especular GGX del sol, con la misma escala de luz
+        let diffuse = surface.diffuse_color();
+        let highlight = surface.specular(&view, &light_dir);
+        let mut final_color = [0.0; 3];
+        for c in 0..3 {
+            final_color[c] = diffuse[c] * lighting[c] + highlight[c] * PI * 0.6 * light_color[c];
+        }
+        
         // Solo calcular reflexión/refracción si la profundidad es baja
         if depth < 1 {
-            // Reflexión (solo si es significativa)
-            if hit.material.reflectivity > 0.3 {
-                let reflected = ray.direction.reflect(&hit.normal);
-                let reflected_ray = Ray::new(hit.point + hit.normal * 0.001, reflected);
-                let reflected_color = trace_ray(&reflected_ray, scene, depth + 1);
-                
-                let ref_amount = hit.material.reflectivity * 0.5;
-                final_color[0] = final_color[0] * (1.0 - ref_amount) 
-                               + reflected_color[0] * ref_amount;
-                final_color[1] = final_color[1] * (1.0 - ref_amount) 
-                               + reflected_color[1] * ref_amount;
-                final_color[2] = final_color[2] * (1.0 - ref_amount) 
-                               + reflected_color[2] * ref_amount;
-            }
+            let cos_theta = view.dot(&normal).min(1.0);
             
-            // Refracción (solo si es muy transparente)
-            if hit.material.transparency > 0.5 {
-                let eta_ratio = if hit.normal.dot(&ray.direction) < 0.0 {
-                    1.0 / hit.material.refractive_index
-                } else {
-                    hit.material.refractive_index
-                };
+            let cone_width = ray.footprint(hit.t);
+            let reflected_ray = Ray::new(hit.point + normal * 0.001, ray.direction.reflect(&normal))
+                .with_cone(cone_width, ray.cone_spread);
+            
+            if hit.material.transparency > 0.0 {
+                // Dieléctrico (agua): Fresnel reparte la luz entre reflexión y refracción
+                let n = hit.material.refractive_index;
+                let eta_ratio = if front_face { 1.0 / n } else { n };
+                let reflectance = schlick_reflectance(cos_theta, eta_ratio, dielectric_f0(n));
                 
-                let normal = if hit.normal.dot(&ray.direction) < 0.0 {
-                    hit.normal
+                let reflected_color = if reflectance > 0.0 {
+                    trace(&reflected_ray, scene, depth + 1, rng).0
                 } else {
-                    -hit.normal
+                    [0.0; 3]
                 };
                 
+                // Sin dirección refractada hay reflexión interna total (reflectance = 1)
+                let mut transmitted = [0.0; 3];
                 if let Some(refracted) = ray.direction.refract(&normal, eta_ratio) {
-                    let refracted_ray = Ray::new(hit.point - normal * 0.001, refracted);
-                    let refracted_color = trace_ray(&refracted_ray, scene, depth + 1);
-                    
-                    let trans_amount = hit.material.transparency * 0.5;
-                    final_color[0] = final_color[0] * (1.0 - trans_amount) 
-                                   + refracted_color[0] * trans_amount;
-                    final_color[1] = final_color[1] * (1.0 - trans_amount) 
-                                   + refracted_color[1] * trans_amount;
-                    final_color[2] = final_color[2] * (1.0 - trans_amount) 
-                                   + refracted_color[2] * trans_amount;
+                    let refracted_ray = Ray::new(hit.point - normal * 0.001, refracted)
+                        .with_cone(cone_width, ray.cone_spread);
+                    let (refracted_color, distance) = trace(&refracted_ray, scene, depth + 1, rng);
+                    let absorption = medium_absorption(&hit.material.absorption, distance, front_face, &refracted_ray, scene);
+                    for c in 0..3 {
+                        transmitted[c] = refracted_color[c] * absorption[c];
+                    }
+                }
+                
+                let amount = hit.material.transparency;
+                for c in 0..3 {
+                    let specular = reflectance * reflected_color[c] + (1.0 - reflectance) * transmitted[c];
+                    final_color[c] = final_color[c] * (1.0 - amount) + specular * amount;
+                }
+            } else if hit.material.has_specular(hit.face) {
+                // Reflejo especular con Fresnel, borroso según la rugosidad
+                let reflectance = surface.fresnel(&view);
+                let reflected_color = glossy_reflection(ray, &hit, &normal, &surface, scene, depth, rng);
+                for c in 0..3 {
+                    final_color[c] = final_color[c] * (1.0 - reflectance[c]) + reflected_color[c] * reflectance[c];
                 }
             }
         }
         
         final_color
     } else {
-        // Skybox - cielo degradado
-        skybox_color(&ray.direction)
+        // Skybox - cielo según la hora del día
+        scene.sky.color(&ray.direction)
+    };
+    
+    // Niebla (distancia o agua) sobre el tramo recorrido por el rayo
+    let fogged = [
+        color[0] * transmittance[0] + inscatter[0],
+        color[1] * transmittance[1] + inscatter[1],
+        color[2] * transmittance[2] + inscatter[2],
+    ];
+    (fogged, distance)
+}
+
+// Media de los rayos reflejados alrededor de la dirección de espejo, muestreados
+// con el lóbulo GGX de la superficie (scene.glossy_samples rayos si es rugosa)
+fn glossy_reflection(ray: &Ray, hit: &HitRecord, normal: &Vec3, surface: &Surface, scene: &Scene, depth: u32, rng: &mut Rng) -> [f64; 3] {
+    let mirror = surface.roughness < MIRROR_ROUGHNESS;
+    let samples = if mirror { 1 } else { scene.glossy_samples.max(1) };
+    let view = -ray.direction;
+    let origin = hit.point + *normal * 0.001;
+    
+    let mut color = [0.0; 3];
+    let mut count = 0;
+    for _ in 0..samples {
+        let direction = if mirror {
+            ray.direction.reflect(&surface.normal)
+        } else {
+            // Las direcciones que quedan bajo la cara no aportan luz
+            match surface.sample_reflection(&view, rng) {
+                Some(direction) if direction.dot(normal) > 0.0 => direction,
+                _ => {
+                    count += 1;
+                    continue;
+                }
+            }
+        };
+        let reflected_ray = Ray::new(origin, direction).with_cone(ray.footprint(hit.t), ray.cone_spread);
+        let sample = trace(&reflected_ray, scene, depth + 1, rng).0;
+        for c in 0..3 {
+            color[c] += sample[c];
+        }
+        count += 1;
     }
+    color.map(|c| c / count as f64)
 }
 
-fn skybox_color(direction: &Vec3) -> [f64; 3] {
-    let t = 0.5 * (direction.normalize().y + 1.0);
+// Superficie PBR del impacto: albedo de la textura, parámetros de los mapas y la
+// normal de sombreado. normal es la geométrica orientada hacia el rayo.
+fn surface_at(ray: &Ray, hit: &HitRecord, normal: &Vec3, scene: &Scene) -> Surface {
+    let footprint = texture_footprint(ray, hit);
+    let texture_color = hit.material.sample(hit.face, hit.u, hit.v, footprint, scene.time, &hit.biome);
+    let pbr = hit.material.pbr(hit.face, hit.u, hit.v, footprint, scene.time);
     
-    // Color del cielo: azul claro arriba, blanco abajo
-    let color1 = [0.5, 0.7, 1.0]; // Azul cielo
-    let color2 = [1.0, 1.0, 1.0]; // Blanco
+    let mut shading_normal = *normal;
+    if let Some(mapped) = pbr.normal {
+        let side = if hit.normal.dot(normal) < 0.0 { -1.0 } else { 1.0 };
+        let perturbed = (hit.tangent * mapped.x + hit.bitangent * mapped.y + hit.normal * mapped.z).normalize() * side;
+        // Si el mapa inclina la normal hasta dejar de ver la cámara, se ignora
+        if perturbed.dot(&-ray.direction) > 0.0 {
+            shading_normal = perturbed;
+        }
+    }
     
-    [
-        color1[0] * t + color2[0] * (1.0 - t),
-        color1[1] * t + color2[1] * (1.0 - t),
-        color1[2] * t + color2[2] * (1.0 - t),
-    ]
+    Surface {
+        normal: shading_normal,
+        albedo: [
+            texture_color[0] as f64 / 255.0,
+            texture_color[1] as f64 / 255.0,
+            texture_color[2] as f64 / 255.0,
+        ],
+        roughness: pbr.roughness,
+        metallic: pbr.metallic,
+        f0: pbr.f0,
+    }
+}
+
+// Tamaño en UV del píxel proyectado sobre la cara; crece en ángulos rasantes.
+// Los cubos miden 1, así que las unidades del mundo coinciden con las UV.
+fn texture_footprint(ray: &Ray, hit: &HitRecord) -> f64 {
+    let cos_theta = ray.direction.dot(&hit.normal).abs().max(0.05);
+    ray.footprint(hit.t) / cos_theta
+}
+
+// Reflectancia de Fresnel (aproximación de Schlick); 1 en reflexión interna total
+fn schlick_reflectance(cos_theta: f64, eta_ratio: f64, f0: f64) -> f64 {
+    let mut cos = cos_theta;
+    if eta_ratio > 1.0 {
+        let sin2_t = eta_ratio * eta_ratio * (1.0 - cos * cos);
+        if sin2_t > 1.0 {
+            return 1.0;
+        }
+        cos = (1.0 - sin2_t).sqrt();
+    }
+    f0 + (1.0 - f0) * (1.0 - cos).powi(5)
+}
+
+fn dielectric_f0(refractive_index: f64) -> f64 {
+    ((refractive_index - 1.0) / (refractive_index + 1.0)).powi(2)
+}
+
+// Beer–Lambert para el tramo recorrido dentro del medio tras entrar en él.
+// Si la niebla del agua ya cubre ese tramo, no se absorbe dos veces.
+fn medium_absorption(absorption: &[f64; 3], distance: f64, entering: bool, ray: &Ray, scene: &Scene) -> [f64; 3] {
+    let fogged = scene.atmosphere.water_fog.is_some() && scene.is_underwater(&ray.origin);
+    if !entering || fogged {
+        return [1.0; 3];
+    }
+    beer_lambert(absorption, distance)
+}
+
+fn beer_lambert(absorption: &[f64; 3], distance: f64) -> [f64; 3] {
+    let mut transmittance = [1.0; 3];
+    for c in 0..3 {
+        // Evita 0 * inf cuando el medio no absorbe
+        if absorption[c] > 0.0 {
+            transmittance[c] = (-absorption[c] * distance).exp();
+        }
+    }
+    transmittance
 }
 
-// Función auxiliar para generar números aleatorios simples
-fn rand_float() -> f64 {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hash, Hasher};
+// Path tracing: rebotes difusos con luz directa (NEE) y MIS para el mapa de entorno
+pub fn trace_path(ray: &Ray, scene: &Scene, rng: &mut Rng) -> [f64; 3] {
+    let mut radiance = [0.0; 3];
+    let mut throughput = [1.0; 3];
+    let mut ray = *ray;
+    // pdf del último rebote de la BSDF; None si viene de la cámara o del agua
+    let mut bsdf_pdf: Option<f64> = None;
+    // Coeficientes de absorción del medio en el que viaja el rayo
+    let mut medium = [0.0; 3];
     
-    let random_state = RandomState::new();
-    let mut hasher = random_state.build_hasher();
+    for bounce in 0..MAX_PATH_DEPTH {
+        let hit = scene.intersect(&ray, 0.001, f64::INFINITY);
+        
+        // Niebla sobre el tramo: añade luz dispersada y atenúa lo que viene detrás
+        let underwater = scene.is_underwater(&ray.origin);
+        let distance = hit.as_ref().map_or(f64::INFINITY, |hit| hit.t);
+        let (transmittance, inscatter) = scene.atmosphere.segment(&scene.sky, &ray.direction, distance, underwater);
+        let absorption = if underwater && scene.atmosphere.water_fog.is_some() {
+            [1.0; 3]
+        } else {
+            beer_lambert(&medium, distance)
+        };
+        for c in 0..3 {
+            radiance[c] += throughput[c] * inscatter[c];
+            throughput[c] *= transmittance[c] * absorption[c];
+        }
+        
+        let hit = match hit {
+            Some(hit) => hit,
+            None => {
+                let sky = escaped_radiance(&ray.direction, scene, bsdf_pdf);
+                for c in 0..3 {
+                    radiance[c] += throughput[c] * sky[c];
+                }
+                break;
+            }
+        };
+        
+        let front_face = hit.normal.dot(&ray.direction) < 0.0;
+        let normal = if front_face { hit.normal } else { -hit.normal };
+        
+        // Eventos especulares elegidos al azar según el material
+        let cos_theta = (-ray.direction).dot(&normal).min(1.0);
+        let cone_width = ray.footprint(hit.t);
+        let reflected_ray = Ray::new(hit.point + normal * 0.001, ray.direction.reflect(&normal))
+            .with_cone(cone_width, ray.cone_spread);
+        if rng.next_f64() < hit.material.transparency {
+            let n = hit.material.refractive_index;
+            let eta_ratio = if front_face { 1.0 / n } else { n };
+            let reflectance = schlick_reflectance(cos_theta, eta_ratio, dielectric_f0(n));
+            ray = match ray.direction.refract(&normal, eta_ratio) {
+                Some(refracted) if rng.next_f64() >= reflectance => {
+                    // Al entrar en el medio, el siguiente tramo se absorbe
+                    medium = if front_face { hit.material.absorption } else { [0.0; 3] };
+                    Ray::new(hit.point - normal * 0.001, refracted).with_cone(cone_width, ray.cone_spread)
+                }
+                _ => reflected_ray,
+            };
+            bsdf_pdf = None;
+            continue;
+        }
+        let surface = surface_at(&ray, &hit, &normal, scene);
+        let view = -ray.direction;
+        
+        // Luz directa
+        let direct = sample_direct_light(&hit.point, &normal, &surface, &view, scene, rng);
+        for c in 0..3 {
+            radiance[c] += throughput[c] * direct[c];
+        }
+        
+        // Rebote según la BSDF (difuso o especular GGX); con el mapa de normales
+        // la dirección puede quedar bajo la cara geométrica y el camino termina
+        let direction = match surface.sample(&view, rng) {
+            Some(direction) if direction.dot(&normal) > 0.0 => direction,
+            _ => break,
+        };
+        let pdf = surface.pdf(&view, &direction);
+        if pdf <= 0.0 {
+            break;
+        }
+        let f = surface.eval(&view, &direction);
+        bsdf_pdf = Some(pdf);
+        ray = Ray::new(hit.point + normal * 0.001, direction).with_cone(cone_width, ray.cone_spread);
+        for c in 0..3 {
+            throughput[c] *= f[c] / pdf;
+        }
+        
+        // Ruleta rusa a partir del tercer rebote
+        if bounce >= 2 {
+            let survive = throughput[0].max(throughput[1]).max(throughput[2]).min(0.95);
+            if rng.next_f64() >= survive {
+                break;
+            }
+            for value in throughput.iter_mut() {
+                *value /= survive;
+            }
+        }
+    }
     
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos()
-        .hash(&mut hasher);
+    radiance
+}
+
+// Radiancia del cielo para un rayo que escapa de la escena
+fn escaped_radiance(direction: &Vec3, scene: &Scene, bsdf_pdf: Option<f64>) -> [f64; 3] {
+    match (scene.sky.environment(), bsdf_pdf) {
+        (Some(map), Some(pdf)) => {
+            let weight = power_heuristic(pdf, map.pdf(direction));
+            let l = map.radiance(direction);
+            [l[0] * weight, l[1] * weight, l[2] * weight]
+        }
+        (Some(map), None) => map.radiance(direction),
+        // El disco solar ya se cuenta como luz directa tras un rebote difuso
+        (None, Some(_)) => scene.sky.radiance(direction),
+        (None, None) => scene.sky.color(direction),
+    }
+}
+
+// Luz directa reflejada hacia view por la superficie (BRDF por el coseno ya aplicada).
+// normal es la geométrica, para que el rayo de sombra salga del bloque.
+fn sample_direct_light(point: &Vec3, normal: &Vec3, surface: &Surface, view: &Vec3, scene: &Scene, rng: &mut Rng) -> [f64; 3] {
+    let origin = *point + *normal * 0.001;
     
-    (hasher.finish() % 10000) as f64 / 10000.0
-}
\ No newline at end of file
+    if let Some(map) = scene.sky.environment() {
+        let (direction, light_pdf) = map.sample(rng);
+        if normal.dot(&direction) <= 0.0 || light_pdf <= 0.0 {
+            return [0.0; 3];
+        }
+        let f = surface.eval(view, &direction);
+        if f.iter().all(|&v| v <= 0.0) || scene.occluded(&Ray::new(origin, direction), f64::INFINITY) {
+            return [0.0; 3];
+        }
+        let weight = power_heuristic(light_pdf, surface.pdf(view, &direction));
+        let l = map.radiance(&direction);
+        let factor = weight / light_pdf;
+        return [l[0] * f[0] * factor, l[1] * f[1] * factor, l[2] * f[2] * factor];
+    }
+    
+    // Sol o luna como luz direccional, con la misma escala que el modo Whitted
+    // (allí el difuso es albedo·0.6·cos, es decir, la BRDF de Lambert por π·0.6)
+    let (light_dir, light_color) = scene.sky.light();
+    if normal.dot(&light_dir) <= 0.0 || scene.occluded(&Ray::new(origin, light_dir), f64::INFINITY) {
+        return [0.0; 3];
+    }
+    let f = surface.eval(view, &light_dir);
+    let factor = PI * 0.6;
+    [light_color[0] * f[0] * factor, light_color[1] * f[1] * factor, light_color[2] * f[2] * factor]
+}
+
+fn power_heuristic(pdf_a: f64, pdf_b: f64) -> f64 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
@@ -0,0 +1,184 @@
+use crate::biome::Colormap;
+use crate::material::{Texture, Tint};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Carpeta de las texturas dentro de un paquete con formato vanilla
+const TEXTURES: &str = "assets/minecraft/textures";
+const COLORMAPS: &str = "assets/minecraft/textures/colormap";
+
+// Texturas incluidas en textures/, por nombre de bloque de Minecraft.
+// El corte del tronco (oak_log_top) no tiene entrada: se dibuja con su color base.
+const BUILTIN_TEXTURES: [(&str, &str); 11] = [
+    ("stone", "textures/stone.png"),
+    ("dirt", "textures/dirt.png"),
+    ("grass_block_top", "textures/grass_top.png"),
+    ("grass_block_side", "textures/grass_side.png"),
+    ("oak_log", "textures/wood.png"),
+    ("oak_leaves", "textures/leaves.png"),
+    ("glass", "textures/glass.png"),
+    ("water_still", "textures/water.png"),
+    ("coal_ore", "textures/coal_ore.png"),
+    ("iron_ore", "textures/iron_ore.png"),
+    ("diamond_ore", "textures/diamond_ore.png"),
+];
+
+enum PackSource {
+    Builtin,
+    Folder(PathBuf),
+    // Las entradas se descomprimen al pedirlas; un paquete vanilla trae cientos
+    // de MB de sonidos e idiomas que el renderer nunca lee
+    Zip(RefCell<zip::ZipArchive<File>>),
+}
+
+// Paquete de recursos de Minecraft (carpeta o .zip) del que salen las texturas
+pub struct ResourcePack {
+    source: PackSource,
+    // Cada textura se carga una sola vez aunque la usen muchos bloques
+    cache: RefCell<HashMap<String, Option<Texture>>>,
+    tints: RefCell<HashMap<String, Tint>>,
+}
+
+impl ResourcePack {
+    // Las texturas de la carpeta textures/ del proyecto
+    pub fn builtin() -> Self {
+        ResourcePack {
+            source: PackSource::Builtin,
+            cache: RefCell::new(HashMap::new()),
+            tints: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn open(path: &str) -> Result<Self, String> {
+        let root = Path::new(path);
+        let source = if root.is_dir() {
+            if !root.join("assets").is_dir() {
+                return Err(format!("{} no contiene una carpeta assets/", path));
+            }
+            PackSource::Folder(root.to_path_buf())
+        } else {
+            let archive = File::open(root)
+                .map_err(|err| err.to_string())
+                .and_then(|file| zip::ZipArchive::new(file).map_err(|err| err.to_string()))
+                .map_err(|err| format!("No se pudo abrir {}: {}", path, err))?;
+            PackSource::Zip(RefCell::new(archive))
+        };
+
+        println!("✓ Paquete de recursos: {}", path);
+        Ok(ResourcePack {
+            source,
+            cache: RefCell::new(HashMap::new()),
+            tints: RefCell::new(HashMap::new()),
+        })
+    }
+
+    // Lee un archivo del paquete por su ruta relativa (p. ej. "assets/minecraft/...")
+    pub fn read(&self, relative: &str) -> Option<Vec<u8>> {
+        match &self.source {
+            PackSource::Builtin => None,
+            PackSource::Folder(root) => fs::read(root.join(relative)).ok(),
+            PackSource::Zip(archive) => {
+                let mut archive = archive.borrow_mut();
+                let mut entry = archive.by_name(relative).ok()?;
+                let mut bytes = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut bytes).ok()?;
+                Some(bytes)
+            }
+        }
+    }
+
+    // Textura de bloque por nombre (p. ej. "grass_block_top"); None usa el color base
+    pub fn texture(&self, name: &str) -> Option<Texture> {
+        self.texture_resource(&format!("block/{}", name))
+    }
+
+    // Textura por su nombre en los modelos JSON (p. ej. "minecraft:block/stone")
+    pub fn texture_resource(&self, resource: &str) -> Option<Texture> {
+        let resource = resource.strip_prefix("minecraft:").unwrap_or(resource);
+        if let Some(texture) = self.cache.borrow().get(resource) {
+            return texture.clone();
+        }
+        let texture = self.load_texture(resource);
+        self.cache.borrow_mut().insert(resource.to_string(), texture.clone());
+        texture
+    }
+
+    // Tintes de bioma. Las texturas de textures/ ya vienen coloreadas, así que
+    // con ellas no hay tinte; en un paquete vanilla son grises y se tiñen.
+    pub fn grass_tint(&self) -> Option<Tint> {
+        self.colormap_tint("grass", [145, 189, 89])
+    }
+
+    pub fn foliage_tint(&self) -> Option<Tint> {
+        self.colormap_tint("foliage", [119, 171, 47])
+    }
+
+    // El color del agua es fijo por bioma; se usa el de las llanuras
+    pub fn water_tint(&self) -> Option<Tint> {
+        match self.source {
+            PackSource::Builtin => None,
+            _ => Some(Tint::Color([63, 118, 228])),
+        }
+    }
+
+    fn colormap_tint(&self, name: &str, fallback: [u8; 3]) -> Option<Tint> {
+        if let PackSource::Builtin = self.source {
+            return None;
+        }
+        if let Some(tint) = self.tints.borrow().get(name) {
+            return Some(tint.clone());
+        }
+
+        let path = format!("{}/{}.png", COLORMAPS, name);
+        let image = self.read(&path).and_then(|bytes| image::load_from_memory(&bytes).ok());
+        let tint = match image {
+            Some(image) => {
+                println!("✓ Mapa de color cargado: {}", path);
+                Tint::Colormap(Arc::new(Colormap::new(image.to_rgb8())))
+            }
+            None => {
+                println!("✗ Advertencia: el paquete no tiene {}, se usa un tinte fijo", path);
+                Tint::Color(fallback)
+            }
+        };
+        self.tints.borrow_mut().insert(name.to_string(), tint.clone());
+        Some(tint)
+    }
+
+    fn load_texture(&self, resource: &str) -> Option<Texture> {
+        if let PackSource::Builtin = self.source {
+            let builtin = resource
+                .strip_prefix("block/")
+                .and_then(|name| BUILTIN_TEXTURES.iter().find(|(block, _)| *block == name));
+            let Some((_, path)) = builtin else {
+                println!("✗ Advertencia: no hay textura incluida para {}, se usa el color base", resource);
+                return None;
+            };
+            return Texture::load(path);
+        }
+
+        match self.read_texture(resource) {
+            Some(texture) => {
+                // Mapas LabPBR opcionales junto a la textura
+                let normal_map = self.read_texture(&format!("{}_n", resource));
+                let specular_map = self.read_texture(&format!("{}_s", resource));
+                Some(texture.with_pbr_maps(normal_map, specular_map))
+            }
+            None => {
+                println!("✗ Advertencia: el paquete no tiene {}.png, se usa el color base", resource);
+                None
+            }
+        }
+    }
+
+    fn read_texture(&self, resource: &str) -> Option<Texture> {
+        let path = format!("{}/{}.png", TEXTURES, resource);
+        let bytes = self.read(&path)?;
+        let mcmeta = self.read(&format!("{}.mcmeta", path));
+        Texture::from_bytes(&path, &bytes, mcmeta.as_deref())
+    }
+}
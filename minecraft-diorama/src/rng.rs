@@ -0,0 +1,36 @@
+// Generador pseudoaleatorio determinista (SplitMix64), barato de crear por píxel
+#[derive(Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    // Secuencia independiente para cada píxel y muestra
+    pub fn for_pixel(x: u32, y: u32, sample: u32) -> Self {
+        let mut rng = Rng::new(((x as u64) << 32) ^ (y as u64));
+        let seed = rng.next_u64() ^ (sample as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        Rng::new(seed)
+    }
+
+    // Estado interno, para seguir la secuencia más tarde con Rng::new
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Número uniforme en [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
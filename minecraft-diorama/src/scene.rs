@@ -1,30 +1,152 @@
-use crate::cube::Cube;
+use crate::cube::{Cube, HitRecord};
+use crate::ray::Ray;
 use crate::vector::Vec3;
 use crate::material::*;
+use crate::sky::Sky;
+use crate::atmosphere::Atmosphere;
+use crate::biome::Biome;
+use crate::resource_pack::ResourcePack;
+use crate::blockstate::{BlockLoader, BlockState};
+use crate::model::builtin_models;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
 pub struct Scene {
     pub cubes: Vec<Cube>,
+    pub sky: Sky,
+    pub atmosphere: Atmosphere,
+    // Reloj de la escena en segundos, para las texturas animadas
+    pub time: f64,
+    // Rayos por reflejo rugoso en el modo Whitted; el visor usa uno
+    pub glossy_samples: u32,
+    // Índice de cubos por celda entera, para consultas por posición
+    grid: HashMap<(i32, i32, i32), usize>,
+    // Biomas asignados a bloques concretos; los demás usan el de set_biome
+    biomes: HashMap<(i32, i32, i32), Biome>,
 }
 
 impl Scene {
     pub fn new() -> Self {
-        Scene { cubes: Vec::new() }
+        Scene {
+            cubes: Vec::new(),
+            sky: Sky::new(10.0),
+            atmosphere: Atmosphere::new(),
+            time: 0.0,
+            glossy_samples: 1,
+            grid: HashMap::new(),
+            biomes: HashMap::new(),
+        }
     }
     
     pub fn add_cube(&mut self, cube: Cube) {
+        self.grid.insert(cell_of(&cube.center()), self.cubes.len());
         self.cubes.push(cube);
     }
     
-    pub fn from_layers(layers_dir: &str) -> Self {
+    pub fn set_texture_filter(&mut self, filter: FilterMode) {
+        for cube in &mut self.cubes {
+            cube.material.set_filter(filter);
+        }
+    }
+    
+    // Bioma por defecto: no cambia los bloques que tienen uno propio
+    pub fn set_biome(&mut self, biome: Biome) {
+        for cube in &mut self.cubes {
+            cube.biome = self.biomes.get(&cell_of(&cube.center())).copied().unwrap_or(biome);
+        }
+    }
+    
+    // Caja que envuelve todos los bloques (min, max)
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let mut min = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for cube in &self.cubes {
+            let center = cube.center();
+            min = Vec3::new(min.x.min(center.x - 0.5), min.y.min(center.y - 0.5), min.z.min(center.z - 0.5));
+            max = Vec3::new(max.x.max(center.x + 0.5), max.y.max(center.y + 0.5), max.z.max(center.z + 0.5));
+        }
+        if self.cubes.is_empty() {
+            (Vec3::zero(), Vec3::zero())
+        } else {
+            (min, max)
+        }
+    }
+    
+    // Nombres de bloque distintos, ordenados
+    pub fn material_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.cubes.iter().map(|cube| cube.material.name.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+    
+    // Bloque que contiene el punto, si lo hay
+    pub fn block_at(&self, point: &Vec3) -> Option<&Cube> {
+        self.grid.get(&cell_of(point)).map(|&index| &self.cubes[index])
+    }
+    
+    pub fn is_underwater(&self, point: &Vec3) -> bool {
+        self.block_at(point).is_some_and(|cube| cube.material.name == "water")
+    }
+    
+    // Intersección más cercana del rayo con la escena
+    pub fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest_hit: Option<HitRecord> = None;
+        let mut closest_t = t_max;
+        
+        for cube in &self.cubes {
+            if let Some(hit) = self.intersect_cube(cube, ray, t_min, closest_t) {
+                closest_t = hit.t;
+                closest_hit = Some(hit);
+            }
+        }
+        
+        closest_hit
+    }
+    
+    // Si la cara de entrada está oculta o es un hueco de la textura,
+    // se prueba la cara de salida del mismo cubo
+    fn intersect_cube(&self, cube: &Cube, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut start = t_min;
+        while let Some(hit) = cube.intersect(ray, start, t_max) {
+            if self.is_hidden_face(cube, &hit) || cube.material.is_cutout(hit.face, hit.u, hit.v, self.time) {
+                start = hit.t + 1e-6;
+                continue;
+            }
+            return Some(hit);
+        }
+        None
+    }
+    
+    // Como en Minecraft, no se dibujan las caras entre dos bloques
+    // transparentes del mismo material (agua con agua)
+    fn is_hidden_face(&self, cube: &Cube, hit: &HitRecord) -> bool {
+        if cube.material.transparency <= 0.0 {
+            return false;
+        }
+        let neighbor_center = cube.center() + hit.normal;
+        self.block_at(&neighbor_center)
+            .is_some_and(|neighbor| neighbor.material.name == cube.material.name)
+    }
+    
+    // Prueba de sombra: ¿hay algún bloque opaco entre el origen y t_max?
+    // Los bloques que se refractan (agua) dejan pasar la luz.
+    pub fn occluded(&self, ray: &Ray, t_max: f64) -> bool {
+        self.cubes
+            .iter()
+            .filter(|cube| cube.material.transparency <= 0.5)
+            .any(|cube| self.intersect_cube(cube, ray, 0.001, t_max).is_some())
+    }
+    
+    pub fn from_layers(layers_dir: &str, pack: &ResourcePack) -> Self {
         let mut scene = Scene::new();
         
         let path = Path::new(layers_dir);
         
         if !path.exists() {
             println!("Carpeta de capas no encontrada. Creando escena de ejemplo...");
-            return Self::create_example_scene();
+            return Self::create_example_scene(pack);
         }
         
         let mut layer_files: Vec<_> = fs::read_dir(path)
@@ -51,7 +173,7 @@ impl Scene {
         
         if layer_files.is_empty() {
             println!("No se encontraron archivos .txt en layers/. Creando escena de ejemplo...");
-            return Self::create_example_scene();
+            return Self::create_example_scene(pack);
         }
         
         for (layer_index, layer_file) in layer_files.iter().enumerate() {
@@ -64,17 +186,26 @@ impl Scene {
             
             let mut blocks_in_layer = 0;
             for (z, line) in lines.iter().enumerate() {
-                for (x, ch) in line.chars().enumerate() {
-                    let block_type = ch.to_string();
-                    let material = Self::get_material_from_char(&block_type);
+                for (x, (block_type, properties)) in parse_layer_line(line).iter().enumerate() {
+                    let block = Self::get_block_from_char(block_type, pack);
                     
-                    if let Some(mat) = material {
+                    if let Some((mat, mut state)) = block {
+                        state.apply(properties);
                         let position = Vec3::new(
                             x as f64,
                             layer_index as f64,
                             z as f64,
                         );
-                        scene.add_cube(Cube::new(position, 1.0, mat));
+                        // biome no es una propiedad del bloque sino de su posición
+                        if let Some(name) = state.remove("biome") {
+                            match Biome::from_name(&name) {
+                                Some(biome) => {
+                                    scene.biomes.insert(cell_of(&position), biome);
+                                }
+                                None => println!("Advertencia: Bioma desconocido '{}'", name),
+                            }
+                        }
+                        scene.add_cube(Cube::new(position, 1.0, mat).with_state(state));
                         blocks_in_layer += 1;
                     }
                 }
@@ -82,43 +213,185 @@ impl Scene {
             println!("  -> {} bloques generados en esta capa", blocks_in_layer);
         }
         
+        let loader = BlockLoader::new(pack);
+        for i in 0..scene.cubes.len() {
+            scene.resolve_block(&loader, i);
+        }
+        scene.connect_fences(&loader);
+        scene.attach_torches(&loader);
+        scene.load_biome_regions(&path.join("biomes.cfg"));
+        scene.set_biome(Biome::plains());
         println!("Escena cargada con {} bloques", scene.cubes.len());
         scene
     }
     
-    fn get_material_from_char(c: &str) -> Option<Material> {
+    // Zonas de bioma opcionales junto a las capas, una por línea: "X1,Z1,X2,Z2 bioma"
+    // cubre esas columnas (ambos extremos incluidos) a cualquier altura. Si dos
+    // zonas se solapan gana la última, y un [biome=...] en la capa gana a todas
+    fn load_biome_regions(&mut self, path: &Path) {
+        let Ok(content) = fs::read_to_string(path) else {
+            return;
+        };
+        let mut regions = Vec::new();
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let region = line.split_once(char::is_whitespace).and_then(|(bounds, name)| {
+                let bounds: Vec<i32> = bounds.split(',').map(|n| n.trim().parse().ok()).collect::<Option<_>>()?;
+                let [x1, z1, x2, z2] = bounds[..] else { return None };
+                Some(((x1.min(x2), z1.min(z2), x1.max(x2), z1.max(z2)), Biome::from_name(name.trim())?))
+            });
+            match region {
+                Some(region) => regions.push(region),
+                None => println!("Advertencia: zona de bioma inválida en {}: '{}'", path.display(), line),
+            }
+        }
+        
+        for cube in &self.cubes {
+            let cell = cell_of(&cube.center());
+            let (x, _, z) = cell;
+            let region = regions
+                .iter()
+                .rev()
+                .find(|((x1, z1, x2, z2), _)| (*x1..=*x2).contains(&x) && (*z1..=*z2).contains(&z));
+            if let Some(&(_, biome)) = region {
+                self.biomes.entry(cell).or_insert(biome);
+            }
+        }
+        println!("Zonas de bioma cargadas desde {}: {}", path.display(), regions.len());
+    }
+    
+    // Modelos del bloque según su nombre y estado: los JSON del paquete de
+    // recursos si los tiene, o si no los modelos incluidos
+    fn resolve_block(&mut self, loader: &BlockLoader, index: usize) {
+        let cube = &mut self.cubes[index];
+        match loader.load(&cube.material.name, &cube.state) {
+            Some(block) => {
+                cube.material.set_texture_slots(block.textures.clone(), block.tinted.clone(), block.overlays.clone());
+                cube.models = block.models.clone();
+            }
+            None => cube.models = builtin_models(&cube.material.name, &cube.state),
+        }
+    }
+    
+    // Las vallas se conectan con cada valla o bloque sólido vecino
+    fn connect_fences(&mut self, loader: &BlockLoader) {
+        let directions = [
+            ("north", Vec3::new(0.0, 0.0, -1.0)),
+            ("east", Vec3::new(1.0, 0.0, 0.0)),
+            ("south", Vec3::new(0.0, 0.0, 1.0)),
+            ("west", Vec3::new(-1.0, 0.0, 0.0)),
+        ];
+        
+        for i in 0..self.cubes.len() {
+            if self.cubes[i].material.name != "oak_fence" {
+                continue;
+            }
+            let center = self.cubes[i].center();
+            for (direction, offset) in &directions {
+                let neighbor = center + *offset;
+                let connected = self.is_solid(&neighbor)
+                    || self.block_at(&neighbor).is_some_and(|cube| cube.material.name == "oak_fence");
+                self.cubes[i].state.set(direction, if connected { "true" } else { "false" });
+            }
+            self.resolve_block(loader, i);
+        }
+    }
+    
+    // Una antorcha sin suelo debajo se cuelga de la primera pared que encuentre
+    // y pasa a ser wall_torch, orientada en dirección contraria a la pared
+    fn attach_torches(&mut self, loader: &BlockLoader) {
+        let walls = [
+            (Vec3::new(-1.0, 0.0, 0.0), "east"),
+            (Vec3::new(0.0, 0.0, -1.0), "south"),
+            (Vec3::new(1.0, 0.0, 0.0), "west"),
+            (Vec3::new(0.0, 0.0, 1.0), "north"),
+        ];
+        
+        for i in 0..self.cubes.len() {
+            if self.cubes[i].material.name != "torch" {
+                continue;
+            }
+            let center = self.cubes[i].center();
+            if self.is_solid(&(center + Vec3::new(0.0, -1.0, 0.0))) {
+                continue;
+            }
+            if let Some(&(_, facing)) = walls.iter().find(|(offset, _)| self.is_solid(&(center + *offset))) {
+                self.cubes[i].material.name = "wall_torch".to_string();
+                self.cubes[i].state.set("facing", facing);
+                self.resolve_block(loader, i);
+            }
+        }
+    }
+    
+    // Bloque completo y opaco en el punto (soporte para vallas y antorchas)
+    fn is_solid(&self, point: &Vec3) -> bool {
+        self.block_at(point).is_some_and(|cube| {
+            cube.is_full_block() && cube.material.transparency <= 0.0 && !cube.material.alpha_cutout
+        })
+    }
+    
+    // Material del bloque y su estado inicial; el nombre del material es el del
+    // bloque en Minecraft, que elige los modelos
+    fn get_block_from_char(c: &str, pack: &ResourcePack) -> Option<(Material, BlockState)> {
         match c {
             "P" => {
                 println!("  [P] Creando Piedra");
-                Some(create_stone_material())
+                Some((create_stone_material(pack), BlockState::default()))
             },
             "A" => {
                 println!("  [A] Creando Agua");
-                Some(create_water_material())
+                Some((create_water_material(pack), BlockState::default()))
             },
             "T" => {
                 println!("  [T] Creando Tierra con césped");
-                Some(create_dirt_material())
+                Some((create_dirt_material(pack), BlockState::default()))
             },
             "M" => {
                 println!("  [M] Creando Madera");
-                Some(create_wood_material())
+                Some((create_wood_material(pack), BlockState::parse("axis=y")))
             },
             "H" => {
                 println!("  [H] Creando Hojas");
-                Some(create_leaves_material())
+                Some((create_leaves_material(pack), BlockState::default()))
             },
             "C" => {
                 println!("  [C] Creando Mineral de Carbón");
-                Some(create_coal_ore_material())
+                Some((create_coal_ore_material(pack), BlockState::default()))
             },
             "I" => {
                 println!("  [I] Creando Mineral de Hierro");
-                Some(create_iron_ore_material())
+                Some((create_iron_ore_material(pack), BlockState::default()))
             },
             "D" => {
                 println!("  [D] Creando Mineral de Diamante");
-                Some(create_diamond_ore_material())
+                Some((create_diamond_ore_material(pack), BlockState::default()))
+            },
+            "V" => {
+                println!("  [V] Creando Vidrio");
+                Some((create_glass_material(pack), BlockState::default()))
+            },
+            "L" => {
+                println!("  [L] Creando Losa de Piedra");
+                Some((create_stone_slab_material(pack), BlockState::parse("type=bottom")))
+            },
+            "E" => {
+                println!("  [E] Creando Escalera de Piedra");
+                Some((create_stone_stairs_material(pack), BlockState::parse("facing=east,half=bottom,shape=straight")))
+            },
+            "F" => {
+                println!("  [F] Creando Valla");
+                Some((create_fence_material(pack), BlockState::default()))
+            },
+            "N" => {
+                println!("  [N] Creando Antorcha");
+                Some((create_torch_material(pack), BlockState::default()))
+            },
+            "O" => {
+                println!("  [O] Creando Amapola");
+                Some((create_poppy_material(pack), BlockState::default()))
+            },
+            "Y" => {
+                println!("  [Y] Creando Hierba");
+                Some((create_short_grass_material(pack), BlockState::default()))
             },
             "X" | "_" | " " => None,
             _ => {
@@ -128,7 +401,7 @@ impl Scene {
         }
     }
     
-    fn create_example_scene() -> Self {
+    fn create_example_scene(pack: &ResourcePack) -> Self {
         let mut scene = Scene::new();
         
         println!("Creando escena de ejemplo simple...");
@@ -138,7 +411,7 @@ impl Scene {
                 scene.add_cube(Cube::new(
                     Vec3::new(x as f64, 0.0, z as f64),
                     1.0,
-                    create_stone_material(),
+                    create_stone_material(pack),
                 ));
             }
         }
@@ -146,14 +419,14 @@ impl Scene {
         scene.add_cube(Cube::new(
             Vec3::new(2.0, 1.0, 2.0),
             1.0,
-            create_water_material(),
+            create_water_material(pack),
         ));
         
         for y in 1..4 {
             scene.add_cube(Cube::new(
                 Vec3::new(1.0, y as f64, 1.0),
                 1.0,
-                create_wood_material(),
+                create_wood_material(pack),
             ));
         }
         
@@ -162,12 +435,45 @@ impl Scene {
                 scene.add_cube(Cube::new(
                     Vec3::new(x as f64, 4.0, z as f64),
                     1.0,
-                    create_leaves_material(),
+                    create_leaves_material(pack),
                 ));
             }
         }
         
+        let loader = BlockLoader::new(pack);
+        for i in 0..scene.cubes.len() {
+            scene.resolve_block(&loader, i);
+        }
+        
         println!("Escena de ejemplo creada con {} bloques", scene.cubes.len());
         scene
     }
-}
\ No newline at end of file
+}
+
+// Cada bloque es una letra, opcionalmente seguida de sus propiedades entre
+// corchetes: "M[axis=x]" o "E[facing=north,half=top]". Los corchetes no ocupan columnas.
+// La propiedad biome ("T[biome=taiga]") da al bloque su propio bioma.
+fn parse_layer_line(line: &str) -> Vec<(String, BlockState)> {
+    let mut blocks: Vec<(String, BlockState)> = Vec::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '[' {
+            blocks.push((ch.to_string(), BlockState::default()));
+            continue;
+        }
+        let properties: String = chars.by_ref().take_while(|&c| c != ']').collect();
+        match blocks.last_mut() {
+            Some((_, state)) => state.apply(&BlockState::parse(&properties)),
+            None => println!("Advertencia: propiedades [{}] sin bloque", properties),
+        }
+    }
+    blocks
+}
+
+fn cell_of(point: &Vec3) -> (i32, i32, i32) {
+    (
+        point.x.round() as i32,
+        point.y.round() as i32,
+        point.z.round() as i32,
+    )
+}
@@ -0,0 +1,273 @@
+use crate::vector::Vec3;
+use crate::preetham::PreethamSky;
+use crate::environment::EnvironmentMap;
+use std::f64::consts::PI;
+
+// Inclinación de la órbita del sol/luna respecto al eje X (grados)
+const ORBIT_TILT: f64 = 20.0;
+
+// Radio angular aparente de los discos (coseno del ángulo)
+const SUN_DISC_COS: f64 = 0.9985;
+const MOON_DISC_COS: f64 = 0.9990;
+
+pub enum SkyModel {
+    // Degradado artístico con tonos de atardecer
+    Gradient,
+    // Cielo físico de Preetham con la turbidez dada (2 = muy claro, 10 = brumoso)
+    Preetham { turbidity: f64 },
+    // Mapa de entorno HDR, que reemplaza al sol y a la luna
+    Environment(EnvironmentMap),
+}
+
+pub struct Sky {
+    // Hora del día en [0, 24): 6 = amanecer, 12 = mediodía, 18 = atardecer
+    time_of_day: f64,
+    model: SkyModel,
+    // Elevación y azimut fijos del sol (grados); si no hay, se usa la hora
+    sun_position: Option<(f64, f64)>,
+    preetham: Option<PreethamSky>,
+}
+
+impl Sky {
+    pub fn new(time_of_day: f64) -> Self {
+        Sky {
+            time_of_day: time_of_day.rem_euclid(24.0),
+            model: SkyModel::Gradient,
+            sun_position: None,
+            preetham: None,
+        }
+    }
+
+    pub fn with_model(mut self, model: SkyModel) -> Self {
+        self.model = model;
+        self.refresh();
+        self
+    }
+
+    // Azimut en grados desde el norte (-Z) hacia el este (+X)
+    pub fn with_sun_position(mut self, elevation: f64, azimuth: f64) -> Self {
+        self.sun_position = Some((elevation, azimuth));
+        self.refresh();
+        self
+    }
+
+    pub fn time_of_day(&self) -> f64 {
+        self.time_of_day
+    }
+
+    pub fn advance(&mut self, hours: f64) {
+        self.time_of_day = (self.time_of_day + hours).rem_euclid(24.0);
+        if self.sun_position.is_none() {
+            self.refresh();
+        }
+    }
+
+    // Recalcula los datos del modelo físico cuando cambia el sol
+    fn refresh(&mut self) {
+        self.preetham = match self.model {
+            SkyModel::Preetham { turbidity } => Some(PreethamSky::new(self.sun_direction(), turbidity)),
+            _ => None,
+        };
+    }
+
+    pub fn environment(&self) -> Option<&EnvironmentMap> {
+        match &self.model {
+            SkyModel::Environment(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    // Ángulo del sol sobre su órbita: 0 al amanecer, PI/2 al mediodía
+    fn sun_angle(&self) -> f64 {
+        (self.time_of_day - 6.0) / 24.0 * 2.0 * PI
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        if let Some((elevation, azimuth)) = self.sun_position {
+            let (el, az) = (elevation.to_radians(), azimuth.to_radians());
+            return Vec3::new(az.sin() * el.cos(), el.sin(), -az.cos() * el.cos());
+        }
+        
+        let angle = self.sun_angle();
+        let tilt = ORBIT_TILT.to_radians();
+        Vec3::new(
+            angle.cos(),
+            angle.sin() * tilt.cos(),
+            angle.sin() * tilt.sin(),
+        ).normalize()
+    }
+
+    pub fn moon_direction(&self) -> Vec3 {
+        -self.sun_direction()
+    }
+
+    // Factor de día: 1 con el sol alto, 0 en plena noche
+    fn day_factor(&self) -> f64 {
+        smoothstep(-0.15, 0.15, self.sun_direction().y)
+    }
+
+    // Intensidad de los tonos de atardecer/amanecer
+    fn sunset_factor(&self) -> f64 {
+        let h = self.sun_direction().y;
+        (-(h / 0.18) * (h / 0.18)).exp()
+    }
+
+    // Luz direccional dominante: el sol de día, la luna de noche
+    pub fn light(&self) -> (Vec3, [f64; 3]) {
+        if let Some(map) = self.environment() {
+            return map.light();
+        }
+        
+        let sun = self.sun_direction();
+
+        if sun.y > -0.05 {
+            // El sol se vuelve anaranjado cerca del horizonte
+            let warmth = smoothstep(0.0, 0.4, sun.y);
+            let intensity = smoothstep(-0.05, 0.1, sun.y);
+            let color = match &self.preetham {
+                Some(preetham) => preetham.sun_color(),
+                None => lerp3([1.0, 0.55, 0.3], [1.0, 0.97, 0.9], warmth),
+            };
+            (sun, scale3(color, intensity))
+        } else {
+            let moon = self.moon_direction();
+            let intensity = 0.3 * smoothstep(-0.05, 0.1, moon.y);
+            (moon, scale3([0.55, 0.65, 0.9], intensity))
+        }
+    }
+
+    pub fn ambient(&self) -> [f64; 3] {
+        if let Some(map) = self.environment() {
+            return map.ambient();
+        }
+        
+        let night = [0.06, 0.07, 0.12];
+        if let Some(preetham) = &self.preetham {
+            return lerp3(night, preetham.ambient(), self.day_factor());
+        }
+        
+        let day = [0.4, 0.4, 0.4];
+        let ambient = lerp3(night, day, self.day_factor());
+        lerp3(ambient, [0.4, 0.3, 0.25], self.sunset_factor() * 0.3)
+    }
+
+    // Color visible del cielo, incluyendo el disco solar
+    pub fn color(&self, direction: &Vec3) -> [f64; 3] {
+        let dir = direction.normalize();
+        add3(self.radiance(&dir), self.sun_disc(&dir))
+    }
+
+    // Cielo sin el disco solar, para cuando el sol ya se muestrea como luz directa
+    pub fn radiance(&self, direction: &Vec3) -> [f64; 3] {
+        if let Some(map) = self.environment() {
+            return map.radiance(direction);
+        }
+        
+        let dir = direction.normalize();
+        let day = self.day_factor();
+        
+        let mut color = match &self.preetham {
+            Some(preetham) => lerp3(self.gradient_color(&dir, 0.0), preetham.radiance(&dir), day),
+            None => self.gradient_color(&dir, day),
+        };
+
+        // Estrellas, solo por encima del horizonte y de noche
+        if dir.y > 0.0 {
+            let star = star_brightness(&self.rotate_to_sky(&dir));
+            let visibility = (1.0 - day) * smoothstep(0.0, 0.2, dir.y);
+            color = add3(color, scale3([0.9, 0.9, 1.0], star * visibility));
+        }
+
+        if dir.dot(&self.moon_direction()) > MOON_DISC_COS {
+            color = add3(color, scale3([0.8, 0.82, 0.9], 1.0 - 0.7 * day));
+        }
+
+        color
+    }
+
+    fn sun_disc(&self, dir: &Vec3) -> [f64; 3] {
+        if self.environment().is_some() {
+            return [0.0; 3];
+        }
+        if let Some(preetham) = &self.preetham {
+            return scale3(preetham.sun_disc(dir), self.day_factor());
+        }
+        
+        let sun = self.sun_direction();
+        if dir.dot(&sun) > SUN_DISC_COS {
+            let warmth = smoothstep(0.0, 0.4, sun.y);
+            scale3(lerp3([1.0, 0.6, 0.3], [1.0, 1.0, 0.9], warmth), 2.0)
+        } else {
+            [0.0; 3]
+        }
+    }
+
+    // Cielo degradado para un factor de día dado
+    fn gradient_color(&self, dir: &Vec3, day: f64) -> [f64; 3] {
+        let sun = self.sun_direction();
+        let sunset = self.sunset_factor();
+
+        // Degradado base: cenit arriba, horizonte abajo
+        let zenith = lerp3([0.01, 0.01, 0.04], [0.5, 0.7, 1.0], day);
+        let mut horizon = lerp3([0.05, 0.06, 0.12], [1.0, 1.0, 1.0], day);
+
+        // Tonos cálidos en el horizonte, más intensos del lado del sol
+        let towards_sun = 0.5 * (Vec3::new(dir.x, 0.0, dir.z).normalize()
+            .dot(&Vec3::new(sun.x, 0.0, sun.z).normalize()) + 1.0);
+        let glow = sunset * (0.35 + 0.65 * towards_sun * towards_sun);
+        horizon = lerp3(horizon, [1.0, 0.45, 0.2], glow);
+
+        let t = 0.5 * (dir.y + 1.0);
+        lerp3(horizon, zenith, t)
+    }
+
+    // Gira la dirección junto con la órbita para que las estrellas se muevan
+    // alrededor del mismo polo que el sol y la luna (fórmula de Rodrigues)
+    fn rotate_to_sky(&self, dir: &Vec3) -> Vec3 {
+        let tilt = ORBIT_TILT.to_radians();
+        let axis = Vec3::new(0.0, -tilt.sin(), tilt.cos());
+        let (s, c) = (-self.sun_angle()).sin_cos();
+        *dir * c + axis.cross(dir) * s + axis * (axis.dot(dir) * (1.0 - c))
+    }
+}
+
+// Estrellas procedurales: celdas sobre la esfera con un hash determinista
+fn star_brightness(dir: &Vec3) -> f64 {
+    let cells = 180.0;
+    let cx = (dir.x * cells).floor() as i64;
+    let cy = (dir.y * cells).floor() as i64;
+    let cz = (dir.z * cells).floor() as i64;
+
+    let mut h = (cx.wrapping_mul(73856093) ^ cy.wrapping_mul(19349663) ^ cz.wrapping_mul(83492791)) as u64;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+
+    let value = (h % 10000) as f64 / 10000.0;
+    if value > 0.996 {
+        (value - 0.996) / 0.004
+    } else {
+        0.0
+    }
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp3(a: [f64; 3], b: [f64; 3], t: f64) -> [f64; 3] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    ]
+}
+
+fn add3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale3(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
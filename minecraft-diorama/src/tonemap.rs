@@ -0,0 +1,132 @@
+
+// Curva que lleva el color lineal HDR al rango [0, 1] de la pantalla
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneMapper {
+    // Recorta lo que pase de 1
+    None,
+    Reinhard,
+    // Ajuste de Stephen Hill de la RRT+ODT de ACES
+    Aces,
+    // AgX de Blender: desatura las luces intensas en vez de virar el tono
+    Agx,
+}
+
+impl ToneMapper {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(ToneMapper::None),
+            "reinhard" => Some(ToneMapper::Reinhard),
+            "aces" => Some(ToneMapper::Aces),
+            "agx" => Some(ToneMapper::Agx),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapper::None => ToneMapper::Reinhard,
+            ToneMapper::Reinhard => ToneMapper::Aces,
+            ToneMapper::Aces => ToneMapper::Agx,
+            ToneMapper::Agx => ToneMapper::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::None => "none",
+            ToneMapper::Reinhard => "reinhard",
+            ToneMapper::Aces => "aces",
+            ToneMapper::Agx => "agx",
+        }
+    }
+
+    // Color lineal a color lineal en [0, 1]
+    fn map(self, color: [f64; 3]) -> [f64; 3] {
+        match self {
+            ToneMapper::None => color.map(|c| c.clamp(0.0, 1.0)),
+            ToneMapper::Reinhard => color.map(|c| c.max(0.0) / (1.0 + c.max(0.0))),
+            ToneMapper::Aces => aces(color),
+            ToneMapper::Agx => agx(color),
+        }
+    }
+}
+
+// Exposición y curva de tonos con las que el framebuffer HDR pasa a 8 bits sRGB
+#[derive(Clone, Copy)]
+pub struct ToneMapping {
+    // En pasos de diafragma: +1 duplica la luz
+    pub exposure: f64,
+    pub mapper: ToneMapper,
+}
+
+impl ToneMapping {
+    pub fn new(exposure: f64, mapper: ToneMapper) -> Self {
+        ToneMapping { exposure, mapper }
+    }
+
+    pub fn map_pixel(&self, color: [f64; 3]) -> [u8; 3] {
+        let scale = 2f64.powf(self.exposure);
+        let mapped = self.mapper.map(color.map(|c| if c.is_finite() { c * scale } else { 0.0 }));
+        mapped.map(|c| (srgb_encode(c) * 255.0 + 0.5) as u8)
+    }
+}
+
+// Función de transferencia sRGB de lineal a codificado
+fn srgb_encode(linear: f64) -> f64 {
+    let c = linear.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn mul(matrix: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn aces(color: [f64; 3]) -> [f64; 3] {
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+    let v = mul(&INPUT, color.map(|c| c.max(0.0)));
+    let fitted = v.map(|c| {
+        let a = c * (c + 0.0245786) - 0.000090537;
+        let b = c * (0.983729 * c + 0.4329510) + 0.238081;
+        a / b
+    });
+    mul(&OUTPUT, fitted).map(|c| c.clamp(0.0, 1.0))
+}
+
+fn agx(color: [f64; 3]) -> [f64; 3] {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    // Codificación logarítmica y curva de contraste aproximada con un polinomio
+    let v = mul(&INSET, color.map(|c| c.max(1e-10)));
+    let curve = v.map(|c| {
+        let x = ((c.log2() - MIN_EV) / (MAX_EV - MIN_EV)).clamp(0.0, 1.0);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    });
+    // La curva devuelve valores con gamma 2.2; se vuelven a lineal
+    mul(&OUTSET, curve).map(|c| c.max(0.0).powf(2.2).clamp(0.0, 1.0))
+}
@@ -49,15 +49,28 @@ impl Vec3 {
         *self - *normal * 2.0 * self.dot(normal)
     }
     
+    // Dos vectores tangentes que forman una base ortonormal con este (normalizado)
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let helper = if self.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let tangent = helper.cross(self).normalize();
+        let bitangent = self.cross(&tangent);
+        (tangent, bitangent)
+    }
+    
     pub fn refract(&self, normal: &Vec3, eta_ratio: f64) -> Option<Self> {
         let cos_theta = (-*self).dot(normal).min(1.0);
         let r_out_perp = (*self + *normal * cos_theta) * eta_ratio;
-        let r_out_parallel_len = -(1.0 - r_out_perp.length_squared()).abs().sqrt();
+        let k = 1.0 - r_out_perp.length_squared();
         
-        if r_out_parallel_len.is_nan() {
+        // k < 0: reflexión interna total, no hay rayo refractado
+        if k < 0.0 {
             None
         } else {
-            Some(r_out_perp + *normal * r_out_parallel_len)
+            Some(r_out_perp - *normal * k.sqrt())
         }
     }
 }